@@ -0,0 +1,392 @@
+use std::ffi::{CStr, CString};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use diesel::{Connection as _, SqliteConnection};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use libsqlite3_sys as ffi;
+use serde::{Serialize, Deserialize};
+
+use crate::config::{Config, CONFIG_PATH};
+use crate::feature::{self, DATABASE_PATH, SCHEMA_VERSION};
+use crate::{Error, Result};
+
+
+/// Increase when the archive layout changes.
+pub const FORMAT_VERSION: u32 = 1;
+
+const FILE_PREFIX: &str = "watchmen-backup-";
+const FILE_SUFFIX: &str = ".tar.gz";
+
+const MANIFEST_NAME: &str = "manifest.json";
+const CONFIG_NAME: &str = "config.json";
+const DATABASE_NAME: &str = "feeder.db";
+
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+	pub format_version: u32,
+	pub schema_version: i32,
+	pub app_version: String,
+	pub created_at: i64
+}
+
+
+/// Starts scheduled backups if they're enabled.
+pub fn schedule(config: &Config) {
+	if !config.backup.enabled {
+		return;
+	}
+
+	let config = config.clone();
+
+	tokio::spawn(async move {
+		let mut interval = tokio::time::interval(Duration::from_secs(config.backup.interval.max(60)));
+
+		// First tick is immediate. Don't back up on every start.
+		interval.tick().await;
+
+		loop {
+			interval.tick().await;
+
+			let config = config.clone();
+
+			let result = tokio::task::spawn_blocking(move || {
+				let directory = Path::new(&config.backup.directory);
+
+				let path = create(&config, directory)?;
+				let removed = rotate(directory, config.backup.keep)?;
+
+				Result::Ok((path, removed))
+			}).await;
+
+			match result {
+				Ok(Ok((path, removed))) => log::info!("Backup created at {:?}. Removed {} old backup(s).", path, removed),
+				Ok(Err(e)) => log::error!("Backup Error: {}", e),
+				Err(e) => log::error!("Backup Task Error: {}", e)
+			}
+		}
+	});
+}
+
+
+/// Writes the database and config into a timestamped archive in the directory. Returns its path.
+pub fn create(config: &Config, directory: &Path) -> Result<PathBuf> {
+	fs::create_dir_all(directory)?;
+
+	let now = chrono::Utc::now();
+
+	let archive_path = directory.join(format!("{}{}{}", FILE_PREFIX, now.format("%Y%m%d-%H%M%S"), FILE_SUFFIX));
+	let db_copy_path = directory.join(format!(".{}{}.db", FILE_PREFIX, now.timestamp()));
+
+	let result = (|| {
+		backup_database(Path::new(DATABASE_PATH), &db_copy_path)?;
+
+		let manifest = Manifest {
+			format_version: FORMAT_VERSION,
+			schema_version: read_schema_version(&db_copy_path)?,
+			app_version: env!("CARGO_PKG_VERSION").to_string(),
+			created_at: now.timestamp()
+		};
+
+		let mut archive = GzEncoder::new(File::create(&archive_path)?, Compression::default());
+
+		write_entry(&mut archive, MANIFEST_NAME, &serde_json::to_vec_pretty(&manifest)?)?;
+		write_entry(&mut archive, CONFIG_NAME, &serde_json::to_vec_pretty(config)?)?;
+		write_entry(&mut archive, DATABASE_NAME, &fs::read(&db_copy_path)?)?;
+
+		// End of archive.
+		archive.write_all(&[0; 1024])?;
+		archive.finish()?;
+
+		Result::Ok(())
+	})();
+
+	let _ = fs::remove_file(&db_copy_path);
+
+	if let Err(e) = result {
+		let _ = fs::remove_file(&archive_path);
+		return Err(e);
+	}
+
+	Ok(archive_path)
+}
+
+/// Removes the oldest backups in the directory so only `keep` are left. Returns how many were removed.
+pub fn rotate(directory: &Path, keep: usize) -> Result<usize> {
+	let mut backups = list(directory)?;
+
+	if backups.len() <= keep {
+		return Ok(0);
+	}
+
+	// Sorted oldest first.
+	let removing = backups.len() - keep;
+
+	for path in backups.drain(..removing) {
+		fs::remove_file(path)?;
+	}
+
+	Ok(removing)
+}
+
+/// Backups in the directory, oldest first.
+pub fn list(directory: &Path) -> Result<Vec<PathBuf>> {
+	if !directory.exists() {
+		return Ok(Vec::new());
+	}
+
+	let mut backups = fs::read_dir(directory)?
+		.filter_map(|v| v.ok())
+		.map(|v| v.path())
+		.filter(|v| {
+			v.file_name()
+				.and_then(|v| v.to_str())
+				.map(|v| v.starts_with(FILE_PREFIX) && v.ends_with(FILE_SUFFIX))
+				.unwrap_or_default()
+		})
+		.collect::<Vec<_>>();
+
+	// Names contain the timestamp.
+	backups.sort();
+
+	Ok(backups)
+}
+
+
+/// Replaces the database and config with the ones in the archive. The app shouldn't be running.
+///
+/// The current files are kept next to them with a `.pre-restore-{timestamp}` extension.
+pub fn restore(archive_path: &Path) -> Result<Manifest> {
+	let mut manifest = None;
+	let mut config = None;
+	let mut database = None;
+
+	for (name, contents) in read_entries(GzDecoder::new(File::open(archive_path)?))? {
+		match name.as_str() {
+			MANIFEST_NAME => manifest = Some(serde_json::from_slice::<Manifest>(&contents)?),
+			CONFIG_NAME => config = Some(contents),
+			DATABASE_NAME => database = Some(contents),
+			_ => ()
+		}
+	}
+
+	let manifest = manifest.ok_or_else(|| Error::MissingField(MANIFEST_NAME.into()))?;
+	let config = config.ok_or_else(|| Error::MissingField(CONFIG_NAME.into()))?;
+	let database = database.ok_or_else(|| Error::MissingField(DATABASE_NAME.into()))?;
+
+	if manifest.format_version > FORMAT_VERSION {
+		return Err(format!("Backup format version {} is newer than the supported version {}.", manifest.format_version, FORMAT_VERSION).into());
+	}
+
+	// Older ones are upgraded when the app starts.
+	if manifest.schema_version > SCHEMA_VERSION {
+		return Err(format!("Backup schema version {} is newer than the supported version {}.", manifest.schema_version, SCHEMA_VERSION).into());
+	}
+
+	// Make sure the config is valid before replacing anything.
+	serde_json::from_slice::<Config>(&config)?;
+
+	let suffix = format!("pre-restore-{}", chrono::Utc::now().timestamp());
+
+	let db_path = Path::new(DATABASE_PATH);
+	let db_restore_path = db_path.with_extension("db.restoring");
+
+	fs::write(&db_restore_path, database)?;
+
+	let schema_version = read_schema_version(&db_restore_path);
+
+	if schema_version.as_ref().ok() != Some(&manifest.schema_version) {
+		let _ = fs::remove_file(&db_restore_path);
+
+		return Err(match schema_version {
+			Ok(version) => format!("Backup database schema version {} doesn't match the manifest ({}).", version, manifest.schema_version).into(),
+			Err(e) => e
+		});
+	}
+
+	if db_path.exists() {
+		fs::rename(db_path, db_path.with_extension(format!("db.{}", suffix)))?;
+	}
+
+	fs::rename(&db_restore_path, db_path)?;
+
+	let config_path = Path::new(CONFIG_PATH);
+
+	if config_path.exists() {
+		fs::rename(config_path, config_path.with_extension(format!("json.{}", suffix)))?;
+	}
+
+	fs::write(config_path, config)?;
+
+	Ok(manifest)
+}
+
+
+fn read_schema_version(path: &Path) -> Result<i32> {
+	let conn = SqliteConnection::establish(&path.to_string_lossy())
+		.map_err(|e| Error::Other(e.to_string()))?;
+
+	Ok(feature::get_schema_version(&conn)?)
+}
+
+
+/// Raw SQLite connection. Diesel doesn't expose its handle.
+struct RawConnection(*mut ffi::sqlite3);
+
+impl RawConnection {
+	#[allow(unsafe_code)]
+	fn open(path: &Path, flags: i32) -> Result<Self> {
+		let c_path = CString::new(path.to_string_lossy().as_bytes()).map_err(|e| Error::Other(e.to_string()))?;
+
+		let mut handle = std::ptr::null_mut();
+
+		let code = unsafe { ffi::sqlite3_open_v2(c_path.as_ptr(), &mut handle, flags, std::ptr::null()) };
+
+		// Handle is still allocated when it fails.
+		let conn = RawConnection(handle);
+
+		if code == ffi::SQLITE_OK {
+			Ok(conn)
+		} else {
+			Err(conn.error())
+		}
+	}
+
+	#[allow(unsafe_code)]
+	fn error(&self) -> Error {
+		if self.0.is_null() {
+			return Error::Other(String::from("SQLite is out of memory."));
+		}
+
+		let message = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0)) };
+
+		Error::Other(format!("SQLite: {}", message.to_string_lossy()))
+	}
+}
+
+impl Drop for RawConnection {
+	#[allow(unsafe_code)]
+	fn drop(&mut self) {
+		unsafe {
+			ffi::sqlite3_close(self.0);
+		}
+	}
+}
+
+/// Copies the database with SQLite's online backup API. It's consistent even while it's being written to.
+#[allow(unsafe_code)]
+fn backup_database(source: &Path, dest: &Path) -> Result<()> {
+	let source = RawConnection::open(source, ffi::SQLITE_OPEN_READONLY)?;
+	let dest = RawConnection::open(dest, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)?;
+
+	let main = CString::new("main").unwrap();
+
+	unsafe {
+		let backup = ffi::sqlite3_backup_init(dest.0, main.as_ptr(), source.0, main.as_ptr());
+
+		if backup.is_null() {
+			return Err(dest.error());
+		}
+
+		let mut attempts = 0;
+
+		let code = loop {
+			match ffi::sqlite3_backup_step(backup, -1) {
+				// More pages left.
+				ffi::SQLITE_OK => (),
+
+				ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED if attempts < 50 => {
+					attempts += 1;
+					std::thread::sleep(Duration::from_millis(100));
+				}
+
+				code => break code
+			}
+		};
+
+		// Finishing also has to happen on failure to release the backup.
+		let finished = ffi::sqlite3_backup_finish(backup);
+
+		if code != ffi::SQLITE_DONE {
+			let message = CStr::from_ptr(ffi::sqlite3_errstr(code));
+			return Err(Error::Other(format!("SQLite backup didn't finish: {}", message.to_string_lossy())));
+		}
+
+		if finished != ffi::SQLITE_OK {
+			return Err(dest.error());
+		}
+	}
+
+	Ok(())
+}
+
+
+// Minimal ustar archive. Only regular files.
+
+const BLOCK_SIZE: usize = 512;
+
+fn write_entry<W: Write>(writer: &mut W, name: &str, contents: &[u8]) -> Result<()> {
+	let mut header = [0u8; BLOCK_SIZE];
+
+	header[..name.len()].copy_from_slice(name.as_bytes());
+	header[100..107].copy_from_slice(b"0000644");
+	header[108..115].copy_from_slice(b"0000000");
+	header[116..123].copy_from_slice(b"0000000");
+	header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+	header[136..147].copy_from_slice(format!("{:011o}", chrono::Utc::now().timestamp()).as_bytes());
+	header[156] = b'0';
+	header[257..263].copy_from_slice(b"ustar\0");
+	header[263..265].copy_from_slice(b"00");
+
+	// Checksum is calculated with its own field as spaces.
+	header[148..156].copy_from_slice(b"        ");
+	let checksum = header.iter().map(|&v| v as u32).sum::<u32>();
+	header[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+
+	writer.write_all(&header)?;
+	writer.write_all(contents)?;
+	writer.write_all(&vec![0; padding(contents.len())])?;
+
+	Ok(())
+}
+
+fn read_entries<R: Read>(mut reader: R) -> Result<Vec<(String, Vec<u8>)>> {
+	let mut entries = Vec::new();
+
+	loop {
+		let mut header = [0u8; BLOCK_SIZE];
+		reader.read_exact(&mut header)?;
+
+		// Two empty blocks end it. One is enough to know.
+		if header.iter().all(|&v| v == 0) {
+			break;
+		}
+
+		let name = String::from_utf8_lossy(&header[..100]).trim_end_matches('\0').to_string();
+
+		let size = std::str::from_utf8(&header[124..136])
+			.ok()
+			.map(|v| v.trim_matches(|c: char| c == '\0' || c == ' '))
+			.and_then(|v| usize::from_str_radix(v, 8).ok())
+			.ok_or_else(|| Error::Other(format!("Invalid archive entry size for \"{}\".", name)))?;
+
+		let mut contents = vec![0; size];
+		reader.read_exact(&mut contents)?;
+
+		let mut pad = vec![0; padding(size)];
+		reader.read_exact(&mut pad)?;
+
+		entries.push((name, contents));
+	}
+
+	Ok(entries)
+}
+
+fn padding(size: usize) -> usize {
+	(BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
@@ -0,0 +1,223 @@
+use std::str::FromStr;
+
+use diesel::{Connection, SqliteConnection};
+use serde::{Serialize, Deserialize};
+
+use crate::feature::models::{EditWatchParserItemModel, QueryId};
+use crate::feature::objects;
+use crate::pattern::UrlPattern;
+use crate::request::feeds::custom::CustomItem;
+use crate::request::watcher::WatchParserItem;
+use crate::{Error, Result};
+
+
+/// Increase when the bundle format changes in a way older versions can't read.
+pub const FORMAT_VERSION: u32 = 1;
+
+
+/// Portable custom feed parsers and watch parsers. IDs aren't kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParserBundle {
+	pub format_version: u32,
+	pub exported_at: i64,
+
+	#[serde(default)]
+	pub custom_items: Vec<CustomItem>,
+	#[serde(default)]
+	pub watch_parsers: Vec<WatchParserItem>
+}
+
+/// What to do when a parser with the same `match_url` already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictMode {
+	#[default]
+	Skip,
+	Overwrite,
+	/// Imports it alongside the existing one with a numbered title. Their `priority` decides which one runs.
+	/// Also numbers the title of new ones if it's taken.
+	Rename
+}
+
+impl FromStr for ConflictMode {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		match value {
+			"skip" => Ok(ConflictMode::Skip),
+			"overwrite" => Ok(ConflictMode::Overwrite),
+			"rename" => Ok(ConflictMode::Rename),
+			_ => Err(format!("Unknown conflict mode \"{}\". Expected skip, overwrite or rename.", value).into())
+		}
+	}
+}
+
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParserKind {
+	CustomItem,
+	WatchParser
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+	Created,
+	Skipped,
+	Overwritten,
+	Renamed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedParser {
+	pub kind: ParserKind,
+	pub title: String,
+	pub match_url: String,
+	pub status: ImportStatus
+}
+
+
+/// Exports the parsers with the IDs. `None` exports all of them.
+pub fn export(custom_item_ids: Option<&[QueryId]>, watch_parser_ids: Option<&[QueryId]>, conn: &SqliteConnection) -> Result<ParserBundle> {
+	let custom_items = objects::get_custom_items(conn)?
+		.into_iter()
+		.filter(|v| custom_item_ids.map(|ids| v.id.map(|id| ids.contains(&id)).unwrap_or_default()).unwrap_or(true))
+		.map(|v| CustomItem { id: None, ..v })
+		.collect();
+
+	let watch_parsers = objects::get_watch_parsers(conn)?
+		.into_iter()
+		.filter(|v| watch_parser_ids.map(|ids| v.id.map(|id| ids.contains(&id)).unwrap_or_default()).unwrap_or(true))
+		.map(|v| WatchParserItem { id: None, ..v })
+		.collect();
+
+	Ok(ParserBundle {
+		format_version: FORMAT_VERSION,
+		exported_at: chrono::Utc::now().timestamp(),
+
+		custom_items,
+		watch_parsers
+	})
+}
+
+
+/// Nothing is imported if any parser in the bundle is invalid.
+pub fn import(bundle: ParserBundle, conflict: ConflictMode, conn: &SqliteConnection) -> Result<Vec<ImportedParser>> {
+	if bundle.format_version > FORMAT_VERSION {
+		return Err(format!("Bundle format version {} is newer than the supported version {}.", bundle.format_version, FORMAT_VERSION).into());
+	}
+
+	for item in &bundle.custom_items {
+		UrlPattern::parse(&item.match_url)?;
+		item.search_opts.validate()?;
+	}
+
+	for item in &bundle.watch_parsers {
+		UrlPattern::parse(&item.match_url)?;
+		item.match_opts.validate()?;
+	}
+
+	conn.transaction(|| import_validated(bundle, conflict, conn))
+}
+
+fn import_validated(bundle: ParserBundle, conflict: ConflictMode, conn: &SqliteConnection) -> Result<Vec<ImportedParser>> {
+	let mut results = Vec::new();
+
+	let mut custom_titles = objects::get_custom_items(conn)?.into_iter().map(|v| v.title).collect::<Vec<_>>();
+
+	for mut item in bundle.custom_items {
+		item.id = None;
+
+		let existing = objects::get_custom_items_by_match_url(&item.match_url, conn)?;
+
+		let status = match (existing.first().and_then(|v| v.id), conflict) {
+			(found, ConflictMode::Rename) if found.is_some() || custom_titles.contains(&item.title) => {
+				item.title = numbered_title(&item.title, &custom_titles);
+				objects::create_custom_item(&item.clone().into(), conn)?;
+				ImportStatus::Renamed
+			}
+
+			(None, _) => {
+				objects::create_custom_item(&item.clone().into(), conn)?;
+				ImportStatus::Created
+			}
+
+			(Some(id), ConflictMode::Overwrite) => {
+				objects::update_custom_item(id, &item.clone().into(), conn)?;
+				ImportStatus::Overwritten
+			}
+
+			(Some(_), _) => ImportStatus::Skipped
+		};
+
+		if !matches!(status, ImportStatus::Skipped) {
+			custom_titles.push(item.title.clone());
+		}
+
+		results.push(ImportedParser {
+			kind: ParserKind::CustomItem,
+			title: item.title,
+			match_url: item.match_url,
+			status
+		});
+	}
+
+	let mut watch_titles = objects::get_watch_parsers(conn)?.into_iter().map(|v| v.title).collect::<Vec<_>>();
+
+	for mut item in bundle.watch_parsers {
+		item.id = None;
+
+		let existing = objects::get_watch_parsers_by_match_url(&item.match_url, conn)?;
+
+		let status = match (existing.first().and_then(|v| v.id), conflict) {
+			(found, ConflictMode::Rename) if found.is_some() || watch_titles.contains(&item.title) => {
+				item.title = numbered_title(&item.title, &watch_titles);
+				objects::create_watch_parser(&item.clone().into(), conn)?;
+				ImportStatus::Renamed
+			}
+
+			(None, _) => {
+				objects::create_watch_parser(&item.clone().into(), conn)?;
+				ImportStatus::Created
+			}
+
+			(Some(id), ConflictMode::Overwrite) => {
+				let edit = EditWatchParserItemModel {
+					title: Some(item.title.clone()),
+					match_url: Some(item.match_url.clone()),
+					description: Some(item.description.clone()),
+					match_opts: Some(serde_json::to_string(&item.match_opts)?),
+					priority: Some(item.priority)
+				};
+
+				objects::update_watch_parser(id, &edit, conn)?;
+				ImportStatus::Overwritten
+			}
+
+			(Some(_), _) => ImportStatus::Skipped
+		};
+
+		if !matches!(status, ImportStatus::Skipped) {
+			watch_titles.push(item.title.clone());
+		}
+
+		results.push(ImportedParser {
+			kind: ParserKind::WatchParser,
+			title: item.title,
+			match_url: item.match_url,
+			status
+		});
+	}
+
+	Ok(results)
+}
+
+
+/// First free "Title (2)", "Title (3)", ..
+fn numbered_title(title: &str, taken: &[String]) -> String {
+	(2..)
+		.map(|number| format!("{} ({})", title, number))
+		.find(|v| !taken.contains(v))
+		.unwrap()
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{TimeZone, Utc};
+
+use crate::feature::Connection;
+use crate::feature::models::QueryId;
+use crate::opml::{self, ImportStatus};
+use crate::bundle::{self, ParserBundle};
+use crate::config::{Config, ConfigManager};
+use crate::backup;
+use crate::Result;
+
+
+const USAGE: &str = "Usage:
+	watchmen                          Start the app.
+	watchmen help                     Show this.
+	watchmen opml import <file>       Import feeds and categories from an OPML file.
+	watchmen opml export [file]       Export feeds and categories as OPML. Prints if no file is given.
+
+	watchmen parsers export [file] [--custom=1,2] [--watch=3]
+	                                  Export custom items and watch parsers as a bundle. All of them unless IDs are given.
+	watchmen parsers import <file> [--conflict=skip|overwrite|rename]
+	                                  Import a parser bundle. Existing parsers with the same match_url are skipped by default.
+
+	watchmen backup create [directory]
+	                                  Back up the database and config. Uses backup.directory from the config by default.
+	watchmen backup list [directory]  List backups, oldest first.
+	watchmen backup restore <file>    Replace the database and config with the backup. Stop the app first.";
+
+
+/// Runs the command in the arguments. Returns false if there wasn't one.
+pub async fn run(args: &[String]) -> Result<bool> {
+	let args = args.iter().map(|v| v.as_str()).collect::<Vec<_>>();
+
+	match args.as_slice() {
+		[] => return Ok(false),
+
+		["opml", "import", path] => {
+			let conn = open_database()?;
+
+			let feeds = opml::parse(&fs::read_to_string(path)?)?;
+
+			for item in opml::import(feeds, conn.connection())? {
+				match item.status {
+					ImportStatus::Added => println!("Added    {}", item.url),
+					ImportStatus::Existing => println!("Existing {}", item.url),
+					ImportStatus::Failed => println!("Failed   {} - {}", item.url, item.error.unwrap_or_default())
+				}
+			}
+		}
+
+		["opml", "export"] => {
+			println!("{}", opml::export(open_database()?.connection())?);
+		}
+
+		["opml", "export", path] => {
+			fs::write(path, opml::export(open_database()?.connection())?)?;
+		}
+
+		["parsers", "export", rest @ ..] => {
+			let (path, flags) = split_flags(rest);
+
+			let custom_ids = flags.iter().find_map(|v| v.strip_prefix("--custom=")).map(parse_ids);
+			let watch_ids = flags.iter().find_map(|v| v.strip_prefix("--watch=")).map(parse_ids);
+
+			// Only the selected kind if one was given.
+			let (custom_ids, watch_ids) = match (custom_ids, watch_ids) {
+				(Some(custom), None) => (Some(custom), Some(Vec::new())),
+				(None, Some(watch)) => (Some(Vec::new()), Some(watch)),
+				v => v
+			};
+
+			let bundle = bundle::export(custom_ids.as_deref(), watch_ids.as_deref(), open_database()?.connection())?;
+			let contents = serde_json::to_string_pretty(&bundle)?;
+
+			match path {
+				Some(path) => fs::write(path, contents)?,
+				None => println!("{}", contents)
+			}
+		}
+
+		["parsers", "import", path, rest @ ..] => {
+			let (_, flags) = split_flags(rest);
+
+			let conflict = match flags.iter().find_map(|v| v.strip_prefix("--conflict=")) {
+				Some(value) => value.parse()?,
+				None => Default::default()
+			};
+
+			let bundle: ParserBundle = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+			for item in bundle::import(bundle, conflict, open_database()?.connection())? {
+				println!("{:<12} {:<13} {} ({})", format!("{:?}", item.status), format!("{:?}", item.kind), item.title, item.match_url);
+			}
+		}
+
+		["backup", "create", rest @ ..] => {
+			let config = load_config()?;
+			let directory = rest.first().copied().unwrap_or(&config.backup.directory);
+
+			println!("{}", backup::create(&config, Path::new(directory))?.display());
+		}
+
+		["backup", "list", rest @ ..] => {
+			let config = load_config()?;
+			let directory = rest.first().copied().unwrap_or(&config.backup.directory);
+
+			for path in backup::list(Path::new(directory))? {
+				println!("{}", path.display());
+			}
+		}
+
+		["backup", "restore", path] => {
+			let manifest = backup::restore(Path::new(path))?;
+
+			println!(
+				"Restored backup from {} (schema version {}).",
+				Utc.timestamp_opt(manifest.created_at, 0).unwrap().to_rfc2822(),
+				manifest.schema_version
+			);
+		}
+
+		["help"] | ["--help"] | ["-h"] => println!("{}", USAGE),
+
+		_ => return Err(format!("Unknown command \"{}\".\n\n{}", args.join(" "), USAGE).into())
+	}
+
+	Ok(true)
+}
+
+
+/// First non-flag argument and the flags.
+fn split_flags<'a>(args: &[&'a str]) -> (Option<&'a str>, Vec<&'a str>) {
+	let (flags, values): (Vec<&str>, Vec<&str>) = args.iter().partition(|v| v.starts_with("--"));
+
+	(values.first().copied(), flags)
+}
+
+fn parse_ids(value: &str) -> Vec<QueryId> {
+	value.split(',').filter_map(|v| v.trim().parse().ok()).collect()
+}
+
+fn load_config() -> Result<Config> {
+	let mut manager = ConfigManager::new();
+	manager.init();
+	manager.load()?;
+
+	Ok(manager.config())
+}
+
+fn open_database() -> Result<Connection> {
+	let conn = Connection::new();
+	conn.init_sql()?;
+
+	Ok(conn)
+}
@@ -6,7 +6,9 @@ use serde_json::{from_str, to_string_pretty};
 
 use crate::error::Error;
 
-pub use opts::Config;
+pub use opts::{Config, ConfigMetrics, ConfigHealth, ConfigFever, ConfigGReader, ConfigBackup};
+
+pub const CONFIG_PATH: &str = "../app/config.json";
 
 #[derive(Default)]
 pub struct ConfigManager {
@@ -20,7 +22,7 @@ impl ConfigManager {
 	}
 
 	pub fn init(&mut self/*, file_path: PathBuf*/) {
-		self.file_path = PathBuf::from("../app/config.json");
+		self.file_path = PathBuf::from(CONFIG_PATH);
 	}
 
 	pub fn load(&mut self) -> Result<(), Error>  {
@@ -81,7 +83,17 @@ mod opts {
 	#[derive(Default, Clone, Serialize, Deserialize)]
 	pub struct Config {
 		pub telegram: ConfigTelegram,
-		pub request: ConfigRequest
+		pub request: ConfigRequest,
+		#[serde(default)]
+		pub metrics: ConfigMetrics,
+		#[serde(default)]
+		pub health: ConfigHealth,
+		#[serde(default)]
+		pub fever: ConfigFever,
+		#[serde(default)]
+		pub greader: ConfigGReader,
+		#[serde(default)]
+		pub backup: ConfigBackup
 	}
 
 	#[derive(Clone, Serialize, Deserialize)]
@@ -101,6 +113,61 @@ mod opts {
 	}
 
 
+	#[derive(Clone, Serialize, Deserialize)]
+	pub struct ConfigMetrics {
+		#[serde(default = "default_true")]
+		pub enabled: bool,
+		/// Adds feed and watcher ids as labels. Creates a series for each feed/watcher.
+		#[serde(default)]
+		pub id_labels: bool
+	}
+
+	impl Default for ConfigMetrics {
+		fn default() -> Self {
+			ConfigMetrics {
+				enabled: true,
+				id_labels: false
+			}
+		}
+	}
+
+
+	#[derive(Clone, Serialize, Deserialize)]
+	pub struct ConfigHealth {
+		/// Seconds since the last finished request cycle before it's considered stalled.
+		#[serde(default = "default_max_cycle_age")]
+		pub max_cycle_age: i64
+	}
+
+	impl Default for ConfigHealth {
+		fn default() -> Self {
+			ConfigHealth {
+				max_cycle_age: default_max_cycle_age()
+			}
+		}
+	}
+
+
+	#[derive(Default, Clone, Serialize, Deserialize)]
+	pub struct ConfigFever {
+		#[serde(default)]
+		pub enabled: bool,
+		/// MD5 of "username:password". It's what Fever clients send.
+		#[serde(default)]
+		pub api_key: String
+	}
+
+	#[derive(Default, Clone, Serialize, Deserialize)]
+	pub struct ConfigGReader {
+		#[serde(default)]
+		pub enabled: bool,
+		#[serde(default)]
+		pub username: String,
+		#[serde(default)]
+		pub password: String
+	}
+
+
 	#[derive(Default, Clone, Serialize, Deserialize)]
 	pub struct ConfigTelegram {
 		#[serde(default = "default_true")]
@@ -110,7 +177,50 @@ mod opts {
 	}
 
 
+	#[derive(Clone, Serialize, Deserialize)]
+	pub struct ConfigBackup {
+		/// Scheduled backups. Manual ones work either way.
+		#[serde(default)]
+		pub enabled: bool,
+		#[serde(default = "default_backup_directory")]
+		pub directory: String,
+		/// Seconds between scheduled backups.
+		#[serde(default = "default_backup_interval")]
+		pub interval: u64,
+		/// Number of backups to keep. Older ones are removed after each scheduled backup.
+		#[serde(default = "default_backup_keep")]
+		pub keep: usize
+	}
+
+	impl Default for ConfigBackup {
+		fn default() -> Self {
+			ConfigBackup {
+				enabled: false,
+				directory: default_backup_directory(),
+				interval: default_backup_interval(),
+				keep: default_backup_keep()
+			}
+		}
+	}
+
+
 	fn default_true() -> bool {
 		true
 	}
-}
\ No newline at end of file
+
+	fn default_max_cycle_age() -> i64 {
+		60 * 10
+	}
+
+	fn default_backup_directory() -> String {
+		String::from("../app/backups")
+	}
+
+	fn default_backup_interval() -> u64 {
+		60 * 60 * 24
+	}
+
+	fn default_backup_keep() -> usize {
+		7
+	}
+}
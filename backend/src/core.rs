@@ -2,15 +2,18 @@ use std::sync::{Arc, Mutex, MutexGuard, Weak};
 use std::time::Duration;
 
 use reqwest::Client;
-use url::Url;
 use log;
 
 use crate::state::CoreState;
 use crate::request::{watcher, default_headers};
+use crate::request::structured::StructuredValue;
+use crate::request::page_text::PageText;
+use crate::request::feeds::{custom, sitemap};
 use crate::request::RequestResults;
 
-use crate::Result;
+use crate::{Result, Error, health, opml, bundle};
 use crate::filter::filter_items;
+use crate::pattern::UrlPattern;
 
 pub struct FeederCore(Arc<Mutex<CoreState>>);
 
@@ -34,11 +37,16 @@ impl FeederCore {
 				// Requests aren't enabled? Break out of loop.
 				if !inner.get_config().request.enabled {
 					log::info!("Requests disabled.");
+					health::scheduler_stopped();
 					break;
 				}
 
+				health::request_cycle_started();
+
 				let resp = inner.run_all_requests(false).await;
 
+				health::request_cycle_finished(&resp);
+
 				for req in &resp.results {
 					match req {
 						RequestResults::Feed(req) => {
@@ -89,12 +97,18 @@ impl FeederCore {
 					}
 				}
 
-				if let Err(e) = objects::insert_request_history(&resp, inner.connection.connection()) {
-					log::error!("Error inserting request history into database: {:?}", e);
-				}
+				let history_group = match objects::insert_request_history(&resp, inner.connection.connection()) {
+					Ok(v) => v,
+					Err(e) => {
+						log::error!("Error inserting request history into database: {:?}", e);
+						None
+					}
+				};
 
 				#[cfg(feature = "website")]
-				let _ = crate::feature::frontend::socket::send_req_resp_to_clients(&resp);
+				if let Err(e) = crate::feature::frontend::socket::send_req_resp_to_clients(&resp, history_group, inner.connection.connection()) {
+					log::error!("Error sending updates to WebSocket clients: {}", e);
+				}
 
 				#[cfg(feature = "telegram")]
 				inner.telegram.send(resp).await;
@@ -152,6 +166,13 @@ impl WeakFeederCore {
 		let conn = inner.connection.connection();
 
 		match rpc {
+			// Web Socket
+
+			Front2CoreNotification::Subscribe { .. } |
+			Front2CoreNotification::Unsubscribe { .. } => {
+				return Err("Subscriptions are only available over the WebSocket.".into());
+			}
+
 			// Dashboard
 
 			Front2CoreNotification::RequestHistoryList { item_count, skip_count } => {
@@ -225,12 +246,23 @@ impl WeakFeederCore {
 
 			Front2CoreNotification::FeedList(..) => {
 				let list = Core2FrontNotification::FeedList {
-					items: objects::get_listeners(conn)?
+					items: objects::get_listeners(conn)?,
+					degraded: objects::get_degraded_parsers(conn)?.into_iter().filter(|v| v.feed_id.is_some()).collect()
 				};
 
 				ctx.respond_with(msg_id_opt, list);
 			}
 
+			Front2CoreNotification::ImportOpml { opml } => {
+				let items = opml::import(opml::parse(&opml)?, conn)?;
+
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::ImportOpml { items });
+			}
+
+			Front2CoreNotification::ExportOpml(..) => {
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::ExportOpml { opml: opml::export(conn)? });
+			}
+
 			Front2CoreNotification::CategoryList(..) => {
 				let list = Core2FrontNotification::CategoryList {
 					categories: objects::get_categories(conn)?,
@@ -241,17 +273,30 @@ impl WeakFeederCore {
 			}
 
 
-			Front2CoreNotification::AddListener { url, custom_item_id } => {
+			Front2CoreNotification::AddListener { url, custom_item_id, url_filter } => {
 				use diesel::RunQueryDsl;
 
+				if let Some(value) = url_filter.as_deref() {
+					sitemap::parse_filter(value)?;
+				}
+
 				// Reqwest Client
 				let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
 
-				let feed = inner.feed_requests.create_new_feed(url, custom_item_id, &req_client, conn).await?;
+				let mut feed = inner.feed_requests.create_new_feed(url, custom_item_id, &req_client, conn).await?;
+
+				if url_filter.is_some() {
+					if feed.feed_type != 3 {
+						return Err(Error::InvalidField("url_filter".into(), "Only sitemap feeds can filter URLs.".into()));
+					}
+
+					feed.url_filter = url_filter;
+				}
 
 				let affected = diesel::insert_into(FeedsSchema::table)
 					.values(&feed)
-					.execute(conn)?;
+					.execute(conn)
+					.map_err(|e| Error::from_url_insert(e, &feed.url))?;
 
 				let new_feed = Core2FrontNotification::NewListener {
 					affected,
@@ -270,6 +315,10 @@ impl WeakFeederCore {
 			Front2CoreNotification::EditListener { id, editing } => {
 				// TODO: Check if changed url. If so; call it and return url it gives us. Will prevent duplicates/redirects.
 
+				if let Some(value) = editing.url_filter.as_deref() {
+					sitemap::parse_filter(value)?;
+				}
+
 				let affected = objects::update_listener(id, &editing, conn)?;
 
 				ctx.respond_with(msg_id_opt, Core2FrontNotification::EditListener { affected, listener: editing });
@@ -336,7 +385,7 @@ impl WeakFeederCore {
 				// Reqwest Client
 				let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
 
-				let html = req_client.get(&url).send().await?.text().await?;
+				let html = crate::http::get(&req_client, &url).await?.text().await?;
 
 				ctx.respond_with(msg_id_opt, Core2FrontNotification::WebpageSource { html });
 			}
@@ -351,11 +400,54 @@ impl WeakFeederCore {
 			}
 
 			Front2CoreNotification::UpdateCustomItem { id, item } => {
-				log::info!("UpdateCustomItem: {:?}", id);
-				log::info!("{:#?}", item);
+				if let Some(opts) = item.search_opts.as_ref() {
+					opts.validate()?;
+				}
+
+				if let Some(match_url) = item.match_url.as_deref() {
+					UrlPattern::parse(match_url)?;
+				}
+
+				let model = item.clone().into();
+
+				let affected = objects::update_custom_item(id, &model, conn)?;
+
+				let new_item = Core2FrontNotification::UpdateCustomItem {
+					affected,
+					item
+				};
+
+				ctx.respond_with(msg_id_opt, new_item);
+			}
+
+			Front2CoreNotification::RemoveCustomItem { id, force } => {
+				let feed_ids = objects::get_feeds_using_custom_item(id, conn)?
+					.into_iter()
+					.map(|v| v.id)
+					.collect::<Vec<_>>();
+
+				if !feed_ids.is_empty() {
+					if !force {
+						return Err(Error::ParserInUse(feed_ids));
+					}
+
+					log::warn!("Removing Custom Item {} used by feeds {:?}", id, feed_ids);
+				}
+
+				let affected = objects::delete_custom_item(id, conn)?;
+
+				let new_item = Core2FrontNotification::RemoveCustomItem {
+					affected,
+					feed_ids
+				};
+
+				ctx.respond_with(msg_id_opt, new_item);
 			}
 
 			Front2CoreNotification::NewCustomItem { item } => {
+				item.search_opts.validate()?;
+				UrlPattern::parse(&item.match_url)?;
+
 				let model = item.clone().into();
 
 				let affected = objects::create_custom_item(&model, conn)?;
@@ -488,23 +580,29 @@ impl WeakFeederCore {
 					.collect();
 
 				let list = Core2FrontNotification::WatcherList {
-					items: watchers
+					items: watchers,
+					degraded: objects::get_degraded_parsers(conn)?.into_iter().filter(|v| v.watch_id.is_some()).collect()
 				};
 
 				ctx.respond_with(msg_id_opt, list);
 			}
 
-			Front2CoreNotification::AddWatcher { url, custom_item_id } => {
-				let watcher = inner.watcher_requests.verify_new_watcher(url, custom_item_id, conn)?;
+			Front2CoreNotification::AddWatcher { url, custom_item_id, structured_data, page_text } => {
+				use diesel::OptionalExtension;
+
+				if objects::get_watcher_by_url(&url, conn).optional()?.is_some() {
+					return Err(Error::DuplicateUrl(url));
+				}
+
+				let watcher = inner.watcher_requests.verify_new_watcher(url, custom_item_id, structured_data, page_text.as_ref(), conn)?;
 
 				let affected = objects::create_watcher(&watcher, conn)?;
 
 				// Cache first History Item.
 				{
-					let parser = if let Some(parser_id) = custom_item_id {
-						objects::get_watch_parser_by_id(parser_id, conn)?
-					} else {
-						objects::get_watch_parser_from_url(Url::parse(&watcher.url).unwrap(), conn)?
+					let parser = match page_text {
+						Some(_) => None,
+						None => watcher::find_parser(&watcher.url, watcher.parser_id, structured_data, conn)?
 					};
 
 					let new_watcher = objects::get_watcher_by_url(&watcher.url, conn)?;
@@ -513,13 +611,15 @@ impl WeakFeederCore {
 					let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
 
 					// let new_item = watcher::get_from_url(&new_watcher.url, conn)?;
-					let new_items = watcher::get_from_url_parser(&req_client, &new_watcher.url, &parser.match_opts).await?;
+					let new_items = watcher::get_from_url(&req_client, &new_watcher.url, structured_data, page_text.as_ref(), parser.as_ref().map(|v| &v.match_opts)).await?;
 
 					objects::create_last_watch_history(&models::NewWatchHistoryModel {
 						watch_id: new_watcher.id,
 						items: serde_json::to_string(&new_items).unwrap(),
 
-						date_added: chrono::Utc::now().timestamp()
+						date_added: chrono::Utc::now().timestamp(),
+
+						diff: None
 					}, conn)?;
 				}
 
@@ -541,6 +641,14 @@ impl WeakFeederCore {
 			Front2CoreNotification::EditWatcher { id, editing } => {
 				// TODO: Check if changed url. If so; call it and return url it gives us. Will prevent duplicates/redirects.
 
+				if let Some(value) = editing.structured_data.as_deref() {
+					value.parse::<StructuredValue>()?;
+				}
+
+				if let Some(value) = editing.page_text.as_deref() {
+					serde_json::from_str::<PageText>(value)?.validate()?;
+				}
+
 				let affected = objects::update_watcher(id, &editing, conn)?;
 
 				ctx.respond_with(msg_id_opt, Core2FrontNotification::EditWatcher { affected, listener: editing });
@@ -553,6 +661,9 @@ impl WeakFeederCore {
 			}
 
 			Front2CoreNotification::NewWatchParser { item } => {
+				UrlPattern::parse(&item.match_url)?;
+				item.match_opts.validate()?;
+
 				let model = item.clone().into();
 
 				let affected = objects::create_watch_parser(&model, conn)?;
@@ -566,6 +677,14 @@ impl WeakFeederCore {
 			}
 
 			Front2CoreNotification::UpdateWatchParser { id, item } => {
+				if let Some(match_url) = item.match_url.as_deref() {
+					UrlPattern::parse(match_url)?;
+				}
+
+				if let Some(opts) = item.match_opts.as_ref() {
+					opts.validate()?;
+				}
+
 				let model = item.clone().into();
 
 				let affected = objects::update_watch_parser(id, &model, conn)?;
@@ -589,6 +708,19 @@ impl WeakFeederCore {
 			}
 
 
+			Front2CoreNotification::ExportParsers { custom_item_ids, watch_parser_ids } => {
+				let bundle = bundle::export(custom_item_ids.as_deref(), watch_parser_ids.as_deref(), conn)?;
+
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::ExportParsers { bundle });
+			}
+
+			Front2CoreNotification::ImportParsers { bundle, conflict } => {
+				let items = bundle::import(bundle, conflict, conn)?;
+
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::ImportParsers { items });
+			}
+
+
 			// History
 			Front2CoreNotification::WatchHistoryList { watch_id, item_count, skip_count } => {
 				ctx.respond_with(msg_id_opt, Core2FrontNotification::WatchHistoryList {
@@ -597,18 +729,59 @@ impl WeakFeederCore {
 			}
 
 			// Test
-			Front2CoreNotification::TestWatcher { url, parser } => {
-				if let Some(parser) = parser {
-					// Reqwest Client
-					let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
+			Front2CoreNotification::TestWatcher { url, parser, structured_data, page_text } => {
+				let (parser, matched, candidates) = match parser {
+					// Page text doesn't use a parser.
+					_ if page_text.is_some() => (None, None, Vec::new()),
 
-					let items = watcher::get_from_url_parser(&req_client, &url, &parser).await?;
+					Some(parser) => (Some(parser), None, Vec::new()),
 
-					ctx.respond_with(msg_id_opt, Core2FrontNotification::TestWatcher { success: true, items });
-				} else {
-					// TODO: Get parser based on url.
-					ctx.respond_with(msg_id_opt, Core2FrontNotification::TestWatcher { success: false, items: Vec::new() });
+					None => {
+						let mut candidates = watcher::find_parsers_for_url(&url, conn)?;
+
+						if candidates.is_empty() {
+							// Structured data doesn't need a parser.
+							if structured_data.is_none() {
+								return Err(Error::ParserNotFound(url));
+							}
+
+							(None, None, candidates)
+						} else {
+							let matched = candidates[0].clone();
+
+							if candidates.len() == 1 {
+								candidates.clear();
+							}
+
+							(Some(matched.parser.match_opts.clone()), Some(matched), candidates)
+						}
+					}
+				};
+
+				if let Some(parser) = parser.as_ref() {
+					parser.validate()?;
+				}
+
+				if let Some(opts) = page_text.as_ref() {
+					opts.validate()?;
 				}
+
+				// Reqwest Client
+				let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
+
+				let (items, nodes, structured, pages) = watcher::get_items(&req_client, &url, structured_data, page_text.as_ref(), parser.as_ref()).await?;
+
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::TestWatcher { success: true, items, nodes, matched, candidates, structured, pages });
+			}
+
+			Front2CoreNotification::TestCustomItem { url, parser } => {
+				parser.validate()?;
+
+				let req_client = Client::builder().default_headers(default_headers()).connect_timeout(Duration::from_secs(10)).build().unwrap();
+
+				let (items, nodes, pages) = custom::test_url_parser(&url, &parser, &req_client).await?;
+
+				ctx.respond_with(msg_id_opt, Core2FrontNotification::TestCustomItem { items, nodes, pages });
 			}
 		}
 
@@ -1,5 +1,7 @@
 use std::{fmt, time::SystemTimeError};
 
+use serde::Serialize;
+
 use regex::Error as RegexError;
 use xpather::Error as XpathError;
 use chrono::ParseError as ChronoError;
@@ -7,8 +9,10 @@ use std::io::Error as IoError;
 use serde_json::Error as JsonError;
 use rss::Error as RssError;
 use reqwest::Error as HttpError;
-use diesel::result::Error as DieselError;
+use diesel::result::{Error as DieselError, DatabaseErrorKind};
 use atom_syndication::Error as AtomError;
+use url::ParseError as UrlError;
+use quick_xml::Error as XmlError;
 
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -28,11 +32,243 @@ pub enum Error {
 
 	Regex(RegexError),
 	Xpath(XpathError),
+	Url(UrlError),
+	Xml(XmlError),
+
+	/// A required value wasn't found while parsing. Contains the field name.
+	MissingField(String),
+	/// No custom item or watch parser matches the URL.
+	ParserNotFound(String),
+	/// A feed or watcher with the URL already exists.
+	DuplicateUrl(String),
+	/// A value sent to us is invalid. Contains the field name and why.
+	InvalidField(String, String),
+	/// The parser can't be removed since feeds use it. Contains the feed IDs.
+	ParserInUse(Vec<i32>),
+	/// A parse step couldn't transform the value. Contains the step and why.
+	Transform(String, String),
 
 	Other(String)
 }
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+	/// Something sent to us was invalid.
+	Input,
+	NotFound,
+	Conflict,
+	/// Requesting a website failed.
+	Network,
+	/// Response couldn't be parsed.
+	Parse,
+	Database,
+	Internal
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ErrorDetails {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub field: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub status: Option<u16>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub ids: Option<Vec<i32>>
+}
+
+/// What's sent to the frontend.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+	pub code: &'static str,
+	pub category: ErrorCategory,
+	pub message: String,
+	pub details: ErrorDetails
+}
+
+
+impl Error {
+	/// Name of the variant. Used as a metrics label.
+	pub fn name(&self) -> &'static str {
+		use Error::*;
+
+		match self {
+			Io(_) => "io",
+			Json(_) => "json",
+			Chrono(_) => "chrono",
+			SystemTime(_) => "system_time",
+
+			Diesel(_) => "diesel",
+			Http(_) => "http",
+
+			Rss(_) => "rss",
+			Atom(_) => "atom",
+
+			Regex(_) => "regex",
+			Xpath(_) => "xpath",
+			Url(_) => "url",
+			Xml(_) => "xml",
+
+			MissingField(_) => "missing_field",
+			ParserNotFound(_) => "parser_not_found",
+			DuplicateUrl(_) => "duplicate_url",
+			InvalidField(..) => "invalid_field",
+			ParserInUse(_) => "parser_in_use",
+			Transform(..) => "transform_failed",
+
+			Other(_) => "other"
+		}
+	}
+
+	/// Stable code the frontend can match on.
+	pub fn code(&self) -> &'static str {
+		use Error::*;
+
+		match self {
+			Io(_) => "io",
+			Json(_) => "invalid_json",
+			Chrono(_) => "invalid_date",
+			SystemTime(_) => "system_time",
+
+			Diesel(DieselError::NotFound) => "not_found",
+			Diesel(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => "duplicate",
+			Diesel(_) => "database",
+
+			Http(e) if e.is_timeout() => "http_timeout",
+			Http(e) if e.is_connect() => "http_connect",
+			Http(e) if e.is_status() => "http_status",
+			Http(e) if e.is_body() || e.is_decode() => "http_body",
+			Http(_) => "http",
+
+			Rss(_) => "invalid_rss",
+			Atom(_) => "invalid_atom",
+
+			Regex(_) => "invalid_regex",
+			Xpath(_) => "xpath",
+			Url(_) => "invalid_url",
+			Xml(_) => "invalid_xml",
+
+			MissingField(_) => "missing_field",
+			ParserNotFound(_) => "parser_not_found",
+			DuplicateUrl(_) => "duplicate_url",
+			InvalidField(..) => "invalid_field",
+			ParserInUse(_) => "parser_in_use",
+			Transform(..) => "transform_failed",
+
+			Other(_) => "other"
+		}
+	}
+
+	pub fn category(&self) -> ErrorCategory {
+		use Error::*;
+
+		match self {
+			Io(_) | SystemTime(_) | Other(_) => ErrorCategory::Internal,
+
+			Json(_) | Chrono(_) | Rss(_) | Atom(_) | Xpath(_) | Xml(_) | MissingField(_) | Transform(..) => ErrorCategory::Parse,
+
+			Diesel(DieselError::NotFound) | ParserNotFound(_) => ErrorCategory::NotFound,
+			Diesel(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) | DuplicateUrl(_) | ParserInUse(_) => ErrorCategory::Conflict,
+			Diesel(_) => ErrorCategory::Database,
+
+			Http(_) => ErrorCategory::Network,
+
+			Regex(_) | Url(_) | InvalidField(..) => ErrorCategory::Input
+		}
+	}
+
+	pub fn details(&self) -> ErrorDetails {
+		use Error::*;
+
+		match self {
+			MissingField(field) | InvalidField(field, _) => ErrorDetails {
+				field: Some(field.clone()),
+				..ErrorDetails::default()
+			},
+
+			ParserInUse(ids) => ErrorDetails {
+				ids: Some(ids.clone()),
+				..ErrorDetails::default()
+			},
+
+			ParserNotFound(url) | DuplicateUrl(url) => ErrorDetails {
+				url: Some(url.clone()),
+				..ErrorDetails::default()
+			},
+
+			Http(e) => ErrorDetails {
+				url: e.url().map(|v| v.to_string()),
+				status: e.status().map(|v| v.as_u16()),
+				..ErrorDetails::default()
+			},
+
+			_ => ErrorDetails::default()
+		}
+	}
+
+	/// Readable message. Unlike Display it doesn't contain the variant name.
+	pub fn message(&self) -> String {
+		use Error::*;
+
+		match self {
+			Io(e) => e.to_string(),
+			Json(e) => e.to_string(),
+			Chrono(e) => e.to_string(),
+			SystemTime(e) => e.to_string(),
+
+			Diesel(DieselError::NotFound) => String::from("Not found."),
+			Diesel(e) => e.to_string(),
+			Http(e) => e.to_string(),
+
+			Rss(e) => e.to_string(),
+			Atom(e) => e.to_string(),
+
+			Regex(e) => e.to_string(),
+			Xpath(e) => format!("{:?}", e),
+			Url(e) => e.to_string(),
+			Xml(e) => e.to_string(),
+
+			MissingField(field) => format!("Missing required field \"{}\".", field),
+			ParserNotFound(url) => format!("No parser matches \"{}\".", url),
+			DuplicateUrl(url) => format!("\"{}\" was already added.", url),
+			InvalidField(field, reason) => format!("Invalid \"{}\": {}", field, reason),
+			ParserInUse(ids) => format!("Parser is used by {} feed(s). Remove them first or force it.", ids.len()),
+			Transform(step, reason) => format!("{} step failed: {}", step, reason),
+
+			Other(e) => e.clone()
+		}
+	}
+
+	pub fn to_response(&self) -> ErrorResponse {
+		ErrorResponse {
+			code: self.code(),
+			category: self.category(),
+			message: self.message(),
+			details: self.details()
+		}
+	}
+
+
+	/// Used when looking up a custom item or watch parser by URL.
+	pub fn from_parser_lookup(error: DieselError, url: &str) -> Self {
+		match error {
+			DieselError::NotFound => Error::ParserNotFound(url.to_string()),
+			e => Error::Diesel(e)
+		}
+	}
+
+	/// Used when inserting something with a unique URL.
+	pub fn from_url_insert(error: DieselError, url: &str) -> Self {
+		match error {
+			DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Error::DuplicateUrl(url.to_string()),
+			e => Error::Diesel(e)
+		}
+	}
+}
+
+
 impl fmt::Display for Error {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		use Error::*;
@@ -50,6 +286,15 @@ impl fmt::Display for Error {
 			Atom(e) => write!(f, "Atom Error: {:?}", e),
 			Http(e) => write!(f, "HTTP Error: {:?}", e),
 			Diesel(e) => write!(f, "Diesel Error: {:?}", e),
+			Url(e) => write!(f, "URL Error: {:?}", e),
+			Xml(e) => write!(f, "XML Error: {:?}", e),
+
+			MissingField(e) => write!(f, "Missing Required Field: {:?}", e),
+			ParserNotFound(e) => write!(f, "Parser Not Found: {:?}", e),
+			DuplicateUrl(e) => write!(f, "Duplicate URL: {:?}", e),
+			InvalidField(field, reason) => write!(f, "Invalid Field: {:?} {:?}", field, reason),
+			ParserInUse(e) => write!(f, "Parser In Use: {:?}", e),
+			Transform(step, reason) => write!(f, "Transform Failed: {:?} {:?}", step, reason),
 
 			Other(e) => write!(f, "Other Error: {:?}", e)
 		}
@@ -57,6 +302,30 @@ impl fmt::Display for Error {
 }
 
 
+#[cfg(feature = "website")]
+impl actix_web::ResponseError for Error {
+	fn status_code(&self) -> actix_web::http::StatusCode {
+		use actix_web::http::StatusCode;
+
+		match self.category() {
+			ErrorCategory::Input => StatusCode::BAD_REQUEST,
+			ErrorCategory::NotFound => StatusCode::NOT_FOUND,
+			ErrorCategory::Conflict => StatusCode::CONFLICT,
+			ErrorCategory::Network if self.code() == "http_timeout" => StatusCode::GATEWAY_TIMEOUT,
+			ErrorCategory::Network => StatusCode::BAD_GATEWAY,
+			ErrorCategory::Parse => StatusCode::UNPROCESSABLE_ENTITY,
+			ErrorCategory::Database | ErrorCategory::Internal => StatusCode::INTERNAL_SERVER_ERROR
+		}
+	}
+
+	fn error_response(&self) -> actix_web::HttpResponse {
+		actix_web::HttpResponse::build(self.status_code()).json(serde_json::json!({
+			"error": self.to_response()
+		}))
+	}
+}
+
+
 impl From<SystemTimeError> for Error {
     fn from(error: SystemTimeError) -> Self {
         Error::SystemTime(error)
@@ -117,6 +386,18 @@ impl From<DieselError> for Error {
 	}
 }
 
+impl From<UrlError> for Error {
+	fn from(error: UrlError) -> Self {
+		Error::Url(error)
+	}
+}
+
+impl From<XmlError> for Error {
+	fn from(error: XmlError) -> Self {
+		Error::Xml(error)
+	}
+}
+
 impl From<String> for Error {
 	fn from(error: String) -> Self {
 		Error::Other(error)
@@ -127,4 +408,4 @@ impl From<&str> for Error {
 	fn from(error: &str) -> Self {
 		Error::Other(error.to_owned())
 	}
-}
\ No newline at end of file
+}
@@ -10,16 +10,20 @@ pub mod analytics;
 pub use models::QueryId;
 
 
+pub const DATABASE_PATH: &str = "../app/feeder.db"; // TODO: Add to config.
+
+/// Stored in `PRAGMA user_version`. Increase when the tables change.
+pub const SCHEMA_VERSION: i32 = 9;
+
+
 pub struct Connection(pub SqliteConnection);
 
 
 impl Connection {
 	pub fn new() -> Self {
-		let database_url = "../app/feeder.db"; // TODO: Add to config.
-
 		Self(
-			SqliteConnection::establish(database_url)
-			.unwrap_or_else(|_| panic!("DB Establishing Connection: {}", database_url))
+			SqliteConnection::establish(DATABASE_PATH)
+			.unwrap_or_else(|_| panic!("DB Establishing Connection: {}", DATABASE_PATH))
 		)
 	}
 
@@ -247,10 +251,136 @@ impl Connection {
 			)"
 		)?;
 
+		// Databases made before versioning are the first version.
+		// Tables above are always created as the first version and migrated from there.
+		if get_schema_version(&self.0)? == 0 {
+			set_schema_version(1, &self.0)?;
+		}
+
+		self.migrate()
+	}
+
+	/// Updates the tables from the stored version up to `SCHEMA_VERSION`.
+	fn migrate(&self) -> QueryResult<()> {
+		let version = get_schema_version(&self.0)?;
+
+		if version < 2 {
+			log::info!("Migrating database to version 2");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Parser priority for when more than one match_url matches.
+				self.0.execute("ALTER TABLE custom_item ADD COLUMN priority INTEGER NOT NULL DEFAULT 0")?;
+				self.0.execute("ALTER TABLE watch_parser ADD COLUMN priority INTEGER NOT NULL DEFAULT 0")?;
+
+				set_schema_version(2, &self.0)
+			})?;
+		}
+
+		if version < 3 {
+			log::info!("Migrating database to version 3");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Parser stats per run. Used to find parsers which stopped working.
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN node_count INTEGER")?;
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN found_count INTEGER")?;
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN field_failures TEXT")?;
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN degraded TEXT")?;
+
+				set_schema_version(3, &self.0)
+			})?;
+		}
+
+		if version < 4 {
+			log::info!("Migrating database to version 4");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Watch schema.org Product data instead of (or before) the parser.
+				self.0.execute("ALTER TABLE watching ADD COLUMN structured_data TEXT")?;
+
+				set_schema_version(4, &self.0)
+			})?;
+		}
+
+		if version < 5 {
+			log::info!("Migrating database to version 5");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Fields whose primary selector stopped working.
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN fallback_fields TEXT")?;
+
+				set_schema_version(5, &self.0)
+			})?;
+		}
+
+		if version < 6 {
+			log::info!("Migrating database to version 6");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Items which were given the fetch time since their date couldn't be parsed.
+				self.0.execute("ALTER TABLE items ADD COLUMN date_unparsed BOOL NOT NULL DEFAULT false")?;
+
+				set_schema_version(6, &self.0)
+			})?;
+		}
+
+		if version < 7 {
+			log::info!("Migrating database to version 7");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Pages a parser fetched with pagination.
+				self.0.execute("ALTER TABLE request_history_item ADD COLUMN page_count INTEGER")?;
+
+				set_schema_version(7, &self.0)
+			})?;
+		}
+
+		if version < 8 {
+			log::info!("Migrating database to version 8");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// Watchers without a parser which compare the page's text.
+				self.0.execute("ALTER TABLE watching ADD COLUMN page_text TEXT")?;
+				self.0.execute("ALTER TABLE watch_history ADD COLUMN diff TEXT")?;
+
+				set_schema_version(8, &self.0)
+			})?;
+		}
+
+		if version < 9 {
+			log::info!("Migrating database to version 9");
+
+			self.0.transaction::<_, diesel::result::Error, _>(|| {
+				// URL pattern for sitemap feeds.
+				self.0.execute("ALTER TABLE feeds ADD COLUMN url_filter TEXT")?;
+
+				set_schema_version(9, &self.0)
+			})?;
+		}
+
 		Ok(())
 	}
 
 	pub fn connection(&self) -> &SqliteConnection {
 		&self.0
 	}
-}
\ No newline at end of file
+}
+
+
+#[derive(QueryableByName)]
+struct UserVersion {
+	#[sql_type = "diesel::sql_types::Integer"]
+	user_version: i32
+}
+
+pub fn get_schema_version(conn: &SqliteConnection) -> QueryResult<i32> {
+	diesel::sql_query("PRAGMA user_version")
+		.get_result::<UserVersion>(conn)
+		.map(|v| v.user_version)
+}
+
+pub fn set_schema_version(version: i32, conn: &SqliteConnection) -> QueryResult<()> {
+	// PRAGMA doesn't accept bound parameters.
+	conn.execute(&format!("PRAGMA user_version = {}", version))?;
+
+	Ok(())
+}
@@ -30,7 +30,10 @@ pub struct FeedItemModel {
 	pub is_starred: bool,
 	pub is_removed: bool,
 	pub tags: String,
-	pub feed_id: QueryId
+	pub feed_id: QueryId,
+
+	/// The source's date couldn't be parsed. `date` is when it was fetched instead.
+	pub date_unparsed: bool
 }
 
 #[derive(Serialize, Deserialize, Debug, Insertable)]
@@ -50,7 +53,10 @@ pub struct NewFeedItemModel {
 	pub is_starred: bool,
 	pub is_removed: bool,
 	pub tags: String,
-	pub feed_id: QueryId
+	pub feed_id: QueryId,
+
+	/// The source's date couldn't be parsed. `date` is when it was fetched instead.
+	pub date_unparsed: bool
 }
 
 
@@ -79,6 +85,9 @@ pub struct FeedModel {
 
 	pub date_added: i64,
 	pub last_called: i64,
+
+	/// Sitemap feeds only keep URLs matching this `match_url` style pattern.
+	pub url_filter: Option<String>,
 }
 
 
@@ -103,6 +112,9 @@ pub struct NewFeedModel {
 
 	pub date_added: i64,
 	pub last_called: i64,
+
+	#[serde(default)]
+	pub url_filter: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, AsChangeset)]
@@ -118,6 +130,8 @@ pub struct EditFeedModel {
 
 	pub sec_interval: Option<i32>,
 	pub remove_after: Option<i32>,
+
+	pub url_filter: Option<String>,
 }
 
 
@@ -238,7 +252,8 @@ pub struct CustomItemModel {
 	pub match_url: String,
 	pub description: String,
 
-	pub search_opts: String
+	pub search_opts: String,
+	pub priority: i32
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Insertable)]
@@ -248,7 +263,8 @@ pub struct NewCustomItemModel {
 	pub match_url: String,
 	pub description: String,
 
-	pub search_opts: String
+	pub search_opts: String,
+	pub priority: i32
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, AsChangeset)]
@@ -258,7 +274,8 @@ pub struct EditCustomItemModel {
 	pub match_url: Option<String>,
 	pub description: Option<String>,
 
-	pub search_opts: Option<String>
+	pub search_opts: Option<String>,
+	pub priority: Option<i32>
 }
 
 
@@ -285,6 +302,11 @@ pub struct WatchingModel {
 
 	pub date_added: i64,
 	pub last_called: i64,
+
+	/// `price` or `availability`. Read from the page's schema.org Product data before using the parser.
+	pub structured_data: Option<String>,
+	/// JSON of `PageText`. The page's text is watched instead of using a parser.
+	pub page_text: Option<String>,
 }
 
 
@@ -304,6 +326,11 @@ pub struct NewWatchingModel {
 
 	pub date_added: i64,
 	pub last_called: i64,
+
+	#[serde(default)]
+	pub structured_data: Option<String>,
+	#[serde(default)]
+	pub page_text: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone, AsChangeset)]
@@ -319,6 +346,9 @@ pub struct EditWatchingModel {
 
 	pub sec_interval: Option<i32>,
 	pub remove_after: Option<i32>,
+
+	pub structured_data: Option<String>,
+	pub page_text: Option<String>,
 }
 
 
@@ -332,7 +362,10 @@ pub struct WatchHistoryModel {
 	pub watch_id: QueryId,
 	pub items: String,
 
-	pub date_added: i64
+	pub date_added: i64,
+
+	/// Line diff from the previous text for page text watchers.
+	pub diff: Option<String>
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Insertable)]
@@ -341,7 +374,10 @@ pub struct NewWatchHistoryModel {
 	pub watch_id: QueryId,
 	pub items: String,
 
-	pub date_added: i64
+	pub date_added: i64,
+
+	/// Line diff from the previous text for page text watchers.
+	pub diff: Option<String>
 }
 
 
@@ -356,7 +392,8 @@ pub struct WatchParserItemModel {
 	pub match_url: String,
 	pub description: String,
 
-	pub match_opts: String
+	pub match_opts: String,
+	pub priority: i32
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Insertable)]
@@ -366,7 +403,8 @@ pub struct NewWatchParserItemModel {
 	pub match_url: String,
 	pub description: String,
 
-	pub match_opts: String
+	pub match_opts: String,
+	pub priority: i32
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, AsChangeset)]
@@ -376,7 +414,8 @@ pub struct EditWatchParserItemModel {
 	pub match_url: Option<String>,
 	pub description: Option<String>,
 
-	pub match_opts: Option<String>
+	pub match_opts: Option<String>,
+	pub priority: Option<i32>
 }
 
 
@@ -408,7 +447,7 @@ pub struct NewRequestHistoryGroupModel {
 
 
 
-#[derive(Serialize, Deserialize, Debug, Clone, Queryable, Identifiable)]
+#[derive(Serialize, Deserialize, Debug, Clone, Queryable, QueryableByName, Identifiable)]
 #[table_name = "request_history_item"]
 pub struct RequestHistoryItemModel {
 	pub id: QueryId,
@@ -424,6 +463,19 @@ pub struct RequestHistoryItemModel {
 	pub duration: Option<i32>,
 
 	pub error: Option<String>,
+
+	/// Nodes matched by the parser's `items`.
+	pub node_count: Option<i32>,
+	/// Nodes which became items.
+	pub found_count: Option<i32>,
+	/// JSON of required field -> nodes it failed on.
+	pub field_failures: Option<String>,
+	/// Why the parser looks broken.
+	pub degraded: Option<String>,
+	/// JSON of field -> nodes where only a fallback selector gave a value.
+	pub fallback_fields: Option<String>,
+	/// Pages fetched by the parser.
+	pub page_count: Option<i32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Insertable)]
@@ -439,4 +491,17 @@ pub struct NewRequestHistoryItemModel {
 	pub duration: Option<i32>,
 
 	pub error: Option<String>,
+
+	/// Nodes matched by the parser's `items`.
+	pub node_count: Option<i32>,
+	/// Nodes which became items.
+	pub found_count: Option<i32>,
+	/// JSON of required field -> nodes it failed on.
+	pub field_failures: Option<String>,
+	/// Why the parser looks broken.
+	pub degraded: Option<String>,
+	/// JSON of field -> nodes where only a fallback selector gave a value.
+	pub fallback_fields: Option<String>,
+	/// Pages fetched by the parser.
+	pub page_count: Option<i32>,
 }
\ No newline at end of file
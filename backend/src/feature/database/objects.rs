@@ -10,14 +10,16 @@ use atom_syndication::Entry as AtomItem;
 use diesel::{SqliteConnection, QueryResult};
 use diesel::prelude::*;
 
-use crate::FilterType;
+use crate::{FilterType, pattern};
 use crate::feature::models::{NewRequestHistoryGroupModel, RequestHistoryGroupModel};
 use crate::request::RequestResults;
 use crate::state::RequestResponse;
 use super::schema::*;
-use super::models::{CategoryModel, CustomItemModel, EditCategoryModel, EditCustomItemModel, EditFeedModel, EditFilterModel, EditWatchParserItemModel, EditWatchingModel, FeedCategoryModel, FeedFilterModel, FeedItemModel, FeedModel, FilterModel, NewCategoryModel, NewCustomItemModel, NewFeedCategoryModel, NewFeedFilterModel, NewFeedItemModel, NewFilterModel, NewRequestHistoryItemModel, NewWatchHistoryModel, NewWatchParserItemModel, NewWatchingModel, QueryId, RequestHistoryItemModel, WatchHistoryModel, WatchParserItemModel, WatchingModel};
+use super::models::{CategoryModel, CustomItemModel, EditCategoryModel, EditCustomItemModel, EditFeedModel, EditFilterModel, EditWatchParserItemModel, EditWatchingModel, FeedCategoryModel, FeedFilterModel, FeedItemModel, FeedModel, FilterModel, NewCategoryModel, NewCustomItemModel, NewFeedCategoryModel, NewFeedFilterModel, NewFeedItemModel, NewFeedModel, NewFilterModel, NewRequestHistoryItemModel, NewWatchHistoryModel, NewWatchParserItemModel, NewWatchingModel, QueryId, RequestHistoryItemModel, WatchHistoryModel, WatchParserItemModel, WatchingModel};
 
 use crate::request::feeds::custom::{CustomItem as CustomItemBase, FoundItem as CustomFoundItem};
+use crate::request::feeds::custom::date::parse_date;
+use crate::request::feeds::sitemap::SitemapUrl;
 use crate::request::watcher::{self, WatchParserItem as WatchParserItemBase};
 
 
@@ -31,7 +33,8 @@ impl From<CustomItemModel> for CustomItemBase {
 			title: val.title,
 			match_url: val.match_url,
 			description: val.description,
-			search_opts: serde_json::from_str(&val.search_opts).unwrap()
+			search_opts: serde_json::from_str(&val.search_opts).unwrap(),
+			priority: val.priority
 		}
 	}
 }
@@ -42,7 +45,8 @@ impl From<CustomItemBase> for NewCustomItemModel {
 			title: item.title,
 			match_url: item.match_url,
 			description: item.description,
-			search_opts: serde_json::to_string(&item.search_opts).unwrap()
+			search_opts: serde_json::to_string(&item.search_opts).unwrap(),
+			priority: item.priority
 		}
 	}
 }
@@ -53,7 +57,8 @@ impl From<CustomItemBase> for EditCustomItemModel {
 			title: Some(item.title),
 			match_url: Some(item.match_url),
 			description: Some(item.description),
-			search_opts: Some(serde_json::to_string(&item.search_opts).unwrap())
+			search_opts: Some(serde_json::to_string(&item.search_opts).unwrap()),
+			priority: Some(item.priority)
 		}
 	}
 }
@@ -65,46 +70,82 @@ pub fn create_custom_item(item: &NewCustomItemModel, conn: &SqliteConnection) ->
 	diesel::insert_into(custom_item).values(item).execute(conn)
 }
 
-pub fn get_custom_item_by_id(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<CustomItemBase> {
+pub fn update_custom_item(f_id: QueryId, item: &EditCustomItemModel, conn: &SqliteConnection) -> QueryResult<usize> {
 	use self::custom_item::dsl::*;
 
-	Ok(
-		custom_item.find(f_id)
-		.get_result::<CustomItemModel>(conn)?
-		.into()
-	)
+	diesel::update(custom_item.filter(id.eq(f_id))).set(item).execute(conn)
 }
 
-pub fn get_custom_item_from_url(f_url: Url, conn: &SqliteConnection) -> QueryResult<CustomItemBase> {
+pub fn delete_custom_item(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<usize> {
 	use self::custom_item::dsl::*;
 
-	let host_str = f_url.host_str().unwrap();
+	diesel::delete(custom_item.filter(id.eq(f_id))).execute(conn)
+}
 
-	let period_count = host_str.bytes().filter(|v| v == &b'.').count();
+/// Custom feeds whose URL resolves to the custom item.
+pub fn get_feeds_using_custom_item(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<Vec<FeedModel>> {
+	use self::feeds::dsl::*;
 
-	let mut values = vec![host_str.to_owned()];
+	let mut found = Vec::new();
 
-	if period_count > 1 {
-		for (i, byte) in host_str.bytes().enumerate() {
-			if byte == b'.' {
-				values.push(format!("*{}", &host_str[i..]));
+	for feed in feeds.filter(feed_type.eq(2)).load::<FeedModel>(conn)? {
+		let item = match Url::parse(&feed.url) {
+			Ok(v) => get_custom_item_from_url(v, conn),
+			_ => continue
+		};
 
-				if period_count == values.len() {
-					break;
-				}
-			}
+		match item {
+			Ok(item) if item.id == Some(f_id) => found.push(feed),
+			Ok(_) | Err(diesel::result::Error::NotFound) => (),
+			Err(e) => return Err(e)
 		}
-	} else {
-		values.push(format!("*.{}", host_str));
 	}
 
+	Ok(found)
+}
+
+/// Exact `match_url`. Unlike `get_custom_item_from_url` it doesn't match wildcards.
+pub fn get_custom_items_by_match_url(f_match_url: &str, conn: &SqliteConnection) -> QueryResult<Vec<CustomItemBase>> {
+	use self::custom_item::dsl::*;
+
 	Ok(
-		custom_item.filter(match_url.eq_any(values))
+		custom_item.filter(match_url.eq(f_match_url))
+		.load::<CustomItemModel>(conn)?
+		.into_iter()
+		.map(|i| i.into())
+		.collect()
+	)
+}
+
+pub fn get_custom_item_by_id(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<CustomItemBase> {
+	use self::custom_item::dsl::*;
+
+	Ok(
+		custom_item.find(f_id)
 		.get_result::<CustomItemModel>(conn)?
 		.into()
 	)
 }
 
+pub fn get_custom_item_from_url(f_url: Url, conn: &SqliteConnection) -> QueryResult<CustomItemBase> {
+	get_custom_items_from_url(&f_url, conn)?
+		.into_iter()
+		.next()
+		.ok_or(diesel::result::Error::NotFound)
+}
+
+/// Every custom item matching the URL. Highest priority and most specific `match_url` first.
+pub fn get_custom_items_from_url(f_url: &Url, conn: &SqliteConnection) -> QueryResult<Vec<CustomItemBase>> {
+	let items = self::custom_item::table.load::<CustomItemModel>(conn)?;
+
+	Ok(
+		pattern::find_matches(f_url, items, |v| (v.match_url.as_str(), v.priority))
+			.into_iter()
+			.map(|i| i.into())
+			.collect()
+	)
+}
+
 pub fn get_custom_items(conn: &SqliteConnection) -> QueryResult<Vec<CustomItemBase>> {
 	Ok(
 		self::custom_item::table.load::<CustomItemModel>(conn)?
@@ -384,6 +425,232 @@ pub fn get_item_count_since(since: i64, conn: &SqliteConnection) -> QueryResult<
 		.get_result(conn)
 }
 
+pub fn get_items_added_since(f_feed_ids: &[QueryId], since: i64, conn: &SqliteConnection) -> QueryResult<Vec<FeedItemModel>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.filter(feed_id.eq_any(f_feed_ids))
+		.filter(date_added.ge(since))
+		.order(date.desc())
+		.load(conn)
+}
+
+/// Oldest first.
+pub fn get_items_after_id(f_id: QueryId, item_count: i64, conn: &SqliteConnection) -> QueryResult<Vec<FeedItemModel>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.filter(id.gt(f_id))
+		.filter(is_removed.eq(false))
+		.order(id.asc())
+		.limit(item_count)
+		.load(conn)
+}
+
+/// Newest first.
+pub fn get_items_before_id(f_id: Option<QueryId>, item_count: i64, conn: &SqliteConnection) -> QueryResult<Vec<FeedItemModel>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.filter(id.lt(f_id.unwrap_or(QueryId::MAX)))
+		.filter(is_removed.eq(false))
+		.order(id.desc())
+		.limit(item_count)
+		.load(conn)
+}
+
+pub fn get_items_by_ids(f_ids: &[QueryId], conn: &SqliteConnection) -> QueryResult<Vec<FeedItemModel>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.filter(id.eq_any(f_ids))
+		.filter(is_removed.eq(false))
+		.order(id.asc())
+		.load(conn)
+}
+
+pub fn get_unread_item_ids(conn: &SqliteConnection) -> QueryResult<Vec<QueryId>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.select(id)
+		.filter(is_read.eq(false))
+		.filter(is_removed.eq(false))
+		.load(conn)
+}
+
+pub fn get_starred_item_ids(conn: &SqliteConnection) -> QueryResult<Vec<QueryId>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.select(id)
+		.filter(is_starred.eq(true))
+		.filter(is_removed.eq(false))
+		.load(conn)
+}
+
+pub fn update_item_read(f_id: QueryId, value: bool, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	diesel::update(items.filter(id.eq(f_id)))
+		.set(is_read.eq(value))
+		.execute(conn)
+}
+
+pub fn update_items_read(f_ids: &[QueryId], value: bool, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	diesel::update(items.filter(id.eq_any(f_ids)))
+		.set(is_read.eq(value))
+		.execute(conn)
+}
+
+pub fn update_item_starred(f_id: QueryId, value: bool, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	diesel::update(items.filter(id.eq(f_id)))
+		.set(is_starred.eq(value))
+		.execute(conn)
+}
+
+/// Labels are stored in `items.tags` as a JSON array.
+pub fn get_item_labels(item: &FeedItemModel) -> Vec<String> {
+	serde_json::from_str(&item.tags).unwrap_or_default()
+}
+
+pub fn update_item_labels(f_id: QueryId, labels: &[String], conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	let value = if labels.is_empty() {
+		String::new()
+	} else {
+		serde_json::to_string(labels).unwrap()
+	};
+
+	diesel::update(items.filter(id.eq(f_id)))
+		.set(tags.eq(value))
+		.execute(conn)
+}
+
+
+#[derive(Debug, Default)]
+pub struct ItemFilter {
+	pub feed_ids: Option<Vec<QueryId>>,
+	/// Items with the label. If feed_ids is also set it matches either.
+	pub label: Option<String>,
+
+	pub is_read: Option<bool>,
+	pub is_starred: Option<bool>,
+
+	/// Compared against `date_added`.
+	pub newer_than: Option<i64>,
+	pub older_than: Option<i64>,
+
+	pub oldest_first: bool,
+	/// Only items after this (date_added, id) in the sort order. The last item of the previous page.
+	pub after: Option<(i64, QueryId)>
+}
+
+pub fn get_filtered_items(filter: &ItemFilter, item_count: i64, conn: &SqliteConnection) -> QueryResult<Vec<FeedItemModel>> {
+	use self::items::dsl::*;
+
+	let mut query = filtered_items_query(filter);
+
+	// Keyset paging so items added or removed between pages don't shift them.
+	query = match (filter.after, filter.oldest_first) {
+		(Some((f_date, f_id)), true) => query.filter(date_added.gt(f_date).or(date_added.eq(f_date).and(id.gt(f_id)))),
+		(Some((f_date, f_id)), false) => query.filter(date_added.lt(f_date).or(date_added.eq(f_date).and(id.lt(f_id)))),
+		(None, _) => query
+	};
+
+	query = if filter.oldest_first {
+		query.order((date_added.asc(), id.asc()))
+	} else {
+		query.order((date_added.desc(), id.desc()))
+	};
+
+	query
+		.limit(item_count)
+		.load(conn)
+}
+
+/// Marks every item the filter matches as read in a single UPDATE.
+pub fn mark_filtered_items_read(filter: &ItemFilter, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	diesel::update(items.filter(id.eq_any(filtered_items_query(filter).select(id))))
+		.set(is_read.eq(true))
+		.execute(conn)
+}
+
+fn filtered_items_query(filter: &ItemFilter) -> items::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+	use self::items::dsl::*;
+
+	let mut query = self::items::table
+		.filter(is_removed.eq(false))
+		.into_boxed();
+
+	// Label is JSON encoded in the tags array.
+	let label_pattern = filter.label.as_ref().map(|label| {
+		format!("%{}%", serde_json::to_string(label).unwrap().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"))
+	});
+
+	match (filter.feed_ids.clone(), label_pattern) {
+		(Some(ids), Some(pattern)) => query = query.filter(feed_id.eq_any(ids).or(tags.like(pattern).escape('\\'))),
+		(Some(ids), None) => query = query.filter(feed_id.eq_any(ids)),
+		(None, Some(pattern)) => query = query.filter(tags.like(pattern).escape('\\')),
+		(None, None) => ()
+	}
+
+	if let Some(value) = filter.is_read {
+		query = query.filter(is_read.eq(value));
+	}
+
+	if let Some(value) = filter.is_starred {
+		query = query.filter(is_starred.eq(value));
+	}
+
+	if let Some(value) = filter.newer_than {
+		query = query.filter(date_added.ge(value));
+	}
+
+	if let Some(value) = filter.older_than {
+		query = query.filter(date_added.lt(value));
+	}
+
+	query
+}
+
+/// (Feed ID, Date Added) of every unread item.
+pub fn get_unread_item_dates(conn: &SqliteConnection) -> QueryResult<Vec<(QueryId, i64)>> {
+	use self::items::dsl::*;
+
+	self::items::table
+		.select((feed_id, date_added))
+		.filter(is_read.eq(false))
+		.filter(is_removed.eq(false))
+		.load(conn)
+}
+
+/// Marks items added on or before `before` as read. Every feed if `f_feed_ids` is None.
+pub fn mark_items_read_before(f_feed_ids: Option<&[QueryId]>, before: i64, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::items::dsl::*;
+
+	match f_feed_ids {
+		Some(f_feed_ids) => {
+			diesel::update(items.filter(feed_id.eq_any(f_feed_ids)).filter(date_added.le(before)))
+				.set(is_read.eq(true))
+				.execute(conn)
+		}
+
+		None => {
+			diesel::update(items.filter(date_added.le(before)))
+				.set(is_read.eq(true))
+				.execute(conn)
+		}
+	}
+}
+
 pub fn remove_item(l_id: QueryId, conn: &SqliteConnection) -> QueryResult<usize> {
 	use self::items::dsl::*;
 
@@ -393,16 +660,16 @@ pub fn remove_item(l_id: QueryId, conn: &SqliteConnection) -> QueryResult<usize>
 
 impl From<&RssItem> for NewFeedItemModel {
 	fn from(item: &RssItem) -> NewFeedItemModel {
+		let date = item.pub_date()
+			.and_then(|d| DateTime::parse_from_rfc2822(d).map(|i| i.naive_utc()).ok());
+
 		let mut new_item = NewFeedItemModel {
 			guid: Default::default(),
 			title: item.title().unwrap_or_default().to_string(),
 			author: item.author().unwrap_or_default().to_string(),
 			content: item.content().unwrap_or_default().to_string(),
 			link: item.link().unwrap_or_default().to_string(),
-			date: item.pub_date()
-					.and_then(|d| DateTime::parse_from_rfc2822(d).map(|i| i.naive_utc()).ok())
-					.unwrap_or_else(|| Utc::now().naive_utc())
-					.timestamp(),
+			date: date.unwrap_or_else(|| Utc::now().naive_utc()).timestamp(),
 
 			hash: Default::default(),
 
@@ -412,7 +679,9 @@ impl From<&RssItem> for NewFeedItemModel {
 			is_removed: false,
 			tags: Default::default(),
 
-			feed_id: 0
+			feed_id: 0,
+
+			date_unparsed: date.is_none()
 		};
 
 		// md5(link + title + authors + content + tags) | Iffy on tags. If tags change then hash needs to change.
@@ -446,6 +715,10 @@ impl From<&RssItem> for NewFeedItemModel {
 
 impl From<&AtomItem> for NewFeedItemModel {
 	fn from(item: &AtomItem) -> NewFeedItemModel {
+		let date = item.published()
+			.or_else(|| Some(item.updated()))
+			.and_then(|d| DateTime::parse_from_rfc3339(d).map(|i| i.naive_utc()).ok());
+
 		let mut new_item = NewFeedItemModel {
 			guid: item.id().to_string(),
 
@@ -453,11 +726,7 @@ impl From<&AtomItem> for NewFeedItemModel {
 			author: item.authors().iter().map(|p| p.name().to_string()).collect::<Vec<String>>().join(" "),
 			content: item.content().unwrap_or(&atom_syndication::Content::default()).value().unwrap_or_default().to_string(),
 			link: item.links().first().map(|l| l.href()).unwrap_or_default().to_string(),
-			date: item.published()
-					.or_else(|| Some(item.updated()))
-					.and_then(|d| DateTime::parse_from_rfc3339(d).map(|i| i.naive_utc()).ok())
-					.unwrap_or_else(|| Utc::now().naive_utc())
-					.timestamp(),
+			date: date.unwrap_or_else(|| Utc::now().naive_utc()).timestamp(),
 
 			hash: Default::default(),
 
@@ -467,7 +736,9 @@ impl From<&AtomItem> for NewFeedItemModel {
 			is_removed: false,
 			tags: Default::default(),
 
-			feed_id: 0
+			feed_id: 0,
+
+			date_unparsed: date.is_none()
 		};
 
 		// md5(link + title + authors + content + tags) | Iffy on tags. If tags change then hash needs to change.
@@ -507,10 +778,7 @@ impl From<CustomFoundItem> for NewFeedItemModel {
 			author: item.author.unwrap_or_default(),
 			content: item.content.unwrap_or_default(),
 			link: item.link,
-			date: Some(item.date)
-				.and_then(|d| DateTime::parse_from_rfc3339(&d).map(|i| i.naive_utc()).ok())
-				.unwrap_or_else(|| Utc::now().naive_utc())
-				.timestamp(),
+			date: item.timestamp.unwrap_or_else(|| Utc::now().timestamp()),
 
 			hash: String::default(),
 
@@ -520,7 +788,9 @@ impl From<CustomFoundItem> for NewFeedItemModel {
 			is_removed: false,
 			tags: String::default(),
 
-			feed_id: 0
+			feed_id: 0,
+
+			date_unparsed: item.timestamp.is_none()
 		};
 
 		// md5(link + title + authors + content + tags) | Iffy on tags. If tags change then hash needs to change.
@@ -551,6 +821,50 @@ impl From<CustomFoundItem> for NewFeedItemModel {
 	}
 }
 
+impl From<&SitemapUrl> for NewFeedItemModel {
+	fn from(item: &SitemapUrl) -> NewFeedItemModel {
+		let date = item.lastmod.as_deref().and_then(|v| parse_date(v, None, Utc::now()));
+
+		let mut new_item = NewFeedItemModel {
+			guid: item.loc.clone(),
+
+			title: item.title(),
+			author: String::default(),
+			content: String::default(),
+			link: item.loc.clone(),
+			date: date.unwrap_or_else(Utc::now).timestamp(),
+
+			hash: String::default(),
+
+			date_added: Utc::now().timestamp(),
+			is_read: false,
+			is_starred: false,
+			is_removed: false,
+			tags: String::default(),
+
+			feed_id: 0,
+
+			date_unparsed: date.is_none()
+		};
+
+		// Only from the URL so a changed lastmod doesn't make it a new item.
+		new_item.hash = {
+			let mut md5 = crypto::md5::Md5::new();
+
+			md5.input_str(&format!(
+				"{}-{}-{}",
+				new_item.link,
+				new_item.title,
+				new_item.author
+			));
+
+			md5.result_str()
+		};
+
+		new_item
+	}
+}
+
 
 
 
@@ -561,6 +875,16 @@ pub fn get_listeners(conn: &SqliteConnection) -> QueryResult<Vec<FeedModel>> {
 }
 
 
+pub fn create_listener(feed: &NewFeedModel, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::feeds::dsl::*;
+	diesel::insert_into(feeds).values(feed).execute(conn)
+}
+
+pub fn get_listener_by_url(f_url: &str, conn: &SqliteConnection) -> QueryResult<Option<FeedModel>> {
+	use self::feeds::dsl::*;
+	feeds.filter(url.eq(f_url)).get_result(conn).optional()
+}
+
 pub fn get_listener_by_id(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<Option<FeedModel>> {
 	use self::feeds::dsl::*;
 	feeds.filter(id.eq(f_id)).get_result(conn).optional()
@@ -591,6 +915,14 @@ pub fn update_listener(f_id: QueryId, edit: &EditFeedModel, conn: &SqliteConnect
 		.execute(conn)
 }
 
+pub fn update_listener_feed_type(f_id: QueryId, value: i32, conn: &SqliteConnection) -> QueryResult<usize> {
+	use self::feeds::dsl::*;
+
+	diesel::update(feeds.filter(id.eq(f_id)))
+		.set(feed_type.eq(value))
+		.execute(conn)
+}
+
 
 
 // Categories
@@ -605,6 +937,31 @@ pub fn get_category(cat_id: QueryId, conn: &SqliteConnection) -> QueryResult<Cat
 	categories.filter(id.eq(cat_id)).get_result(conn)
 }
 
+pub fn get_category_by_name(cat_name: &str, conn: &SqliteConnection) -> QueryResult<Option<CategoryModel>> {
+	use self::categories::dsl::*;
+
+	categories.filter(name_lowercase.eq(cat_name.to_lowercase())).get_result(conn).optional()
+}
+
+/// Categories are created with the next position.
+pub fn get_or_create_category(cat_name: &str, conn: &SqliteConnection) -> QueryResult<CategoryModel> {
+	if let Some(category) = get_category_by_name(cat_name, conn)? {
+		return Ok(category);
+	}
+
+	create_category(
+		&NewCategoryModel {
+			position: get_categories(conn)?.len() as i32,
+			name: cat_name.to_string(),
+			name_lowercase: cat_name.to_lowercase(),
+			date_added: chrono::Utc::now().timestamp()
+		},
+		conn
+	)?;
+
+	get_category_by_name(cat_name, conn)?.ok_or(diesel::result::Error::NotFound)
+}
+
 pub fn create_category(category: &NewCategoryModel, conn: &SqliteConnection) -> QueryResult<usize> {
 	use self::categories::dsl::*;
 	diesel::insert_into(categories).values(category).execute(conn)
@@ -715,7 +1072,8 @@ impl From<WatchParserItemModel> for WatchParserItemBase {
 			title: val.title,
 			match_url: val.match_url,
 			description: val.description,
-			match_opts: serde_json::from_str(&val.match_opts).unwrap()
+			match_opts: serde_json::from_str(&val.match_opts).unwrap(),
+			priority: val.priority
 		}
 	}
 }
@@ -739,6 +1097,19 @@ pub fn delete_watch_parser(f_id: QueryId, conn: &SqliteConnection) -> QueryResul
 }
 
 
+/// Exact `match_url`. Unlike `get_watch_parser_from_url` it doesn't match wildcards.
+pub fn get_watch_parsers_by_match_url(f_match_url: &str, conn: &SqliteConnection) -> QueryResult<Vec<WatchParserItemBase>> {
+	use self::watch_parser::dsl::*;
+
+	Ok(
+		watch_parser.filter(match_url.eq(f_match_url))
+		.load::<WatchParserItemModel>(conn)?
+		.into_iter()
+		.map(|i| i.into())
+		.collect()
+	)
+}
+
 pub fn get_watch_parser_by_id(f_id: QueryId, conn: &SqliteConnection) -> QueryResult<WatchParserItemBase> {
 	use self::watch_parser::dsl::*;
 
@@ -750,32 +1121,21 @@ pub fn get_watch_parser_by_id(f_id: QueryId, conn: &SqliteConnection) -> QueryRe
 }
 
 pub fn get_watch_parser_from_url(f_url: Url, conn: &SqliteConnection) -> QueryResult<WatchParserItemBase> {
-	use self::watch_parser::dsl::*;
-
-	let host_str = f_url.host_str().unwrap();
-
-	let period_count = host_str.bytes().filter(|v| v == &b'.').count();
-
-	let mut values = vec![host_str.to_owned()];
+	get_watch_parsers_from_url(&f_url, conn)?
+		.into_iter()
+		.next()
+		.ok_or(diesel::result::Error::NotFound)
+}
 
-	if period_count > 1 {
-		for (i, byte) in host_str.bytes().enumerate() {
-			if byte == b'.' {
-				values.push(format!("*{}", &host_str[i..]));
-
-				if period_count == values.len() {
-					break;
-				}
-			}
-		}
-	} else {
-		values.push(format!("*.{}", host_str));
-	}
+/// Every watch parser matching the URL. Highest priority and most specific `match_url` first.
+pub fn get_watch_parsers_from_url(f_url: &Url, conn: &SqliteConnection) -> QueryResult<Vec<WatchParserItemBase>> {
+	let items = self::watch_parser::table.load::<WatchParserItemModel>(conn)?;
 
 	Ok(
-		watch_parser.filter(match_url.eq_any(values))
-		.get_result::<WatchParserItemModel>(conn)?
-		.into()
+		pattern::find_matches(f_url, items, |v| (v.match_url.as_str(), v.priority))
+			.into_iter()
+			.map(|i| i.into())
+			.collect()
 	)
 }
 
@@ -798,7 +1158,9 @@ pub struct WatchHistoryBase {
 	pub watch_id: QueryId,
 	pub items: Vec<watcher::FoundItem>,
 
-	pub date_added: i64
+	pub date_added: i64,
+
+	pub diff: Option<String>
 }
 
 impl From<WatchHistoryModel> for WatchHistoryBase {
@@ -809,7 +1171,9 @@ impl From<WatchHistoryModel> for WatchHistoryBase {
 			watch_id: history.watch_id,
 			items: serde_json::from_str(&history.items).unwrap(),
 
-			date_added: history.date_added
+			date_added: history.date_added,
+
+			diff: history.diff
 		}
 	}
 }
@@ -868,6 +1232,19 @@ pub fn get_watch_history_list(f_watch_id: Option<QueryId>, item_count: i64, skip
 }
 
 
+pub fn get_previous_watch_history(f_watch_id: QueryId, f_history_id: QueryId, conn: &SqliteConnection) -> QueryResult<Option<WatchHistoryBase>> {
+	use self::watch_history::dsl::*;
+
+	watch_history
+	.filter(watch_id.eq(f_watch_id))
+	.filter(id.lt(f_history_id))
+	.order_by(id.desc())
+	.first::<WatchHistoryModel>(conn)
+	.map(WatchHistoryBase::from)
+	.optional()
+}
+
+
 pub fn create_last_watch_history(item: &NewWatchHistoryModel, conn: &SqliteConnection) -> QueryResult<usize> {
 	use self::watch_history::dsl::*;
 
@@ -876,9 +1253,61 @@ pub fn create_last_watch_history(item: &NewWatchHistoryModel, conn: &SqliteConne
 
 
 
+// Watch History Changes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchHistoryChanges {
+	pub history_id: QueryId,
+	pub watch_id: QueryId,
+
+	pub changes: Vec<watcher::FoundItemChange>,
+	/// Line diff of the page text if the watcher doesn't use a parser.
+	pub diff: Option<String>,
+
+	pub date_added: i64
+}
+
+/// Compares each history item against the one before it. Most recent first.
+pub fn get_watch_history_changes(f_watch_id: Option<QueryId>, item_count: i64, conn: &SqliteConnection) -> QueryResult<Vec<WatchHistoryChanges>> {
+	let mut found = Vec::new();
+
+	for history in get_watch_history_list(f_watch_id, item_count, 0, conn)? {
+		if let Some(changes) = get_watch_history_change(&history, conn)? {
+			found.push(changes);
+		}
+	}
+
+	Ok(found)
+}
+
+/// Compares the history item against the one before it.
+/// The first history item of a watcher has nothing to compare against so it returns None. Same if nothing changed.
+pub fn get_watch_history_change(history: &WatchHistoryBase, conn: &SqliteConnection) -> QueryResult<Option<WatchHistoryChanges>> {
+	let previous = match get_previous_watch_history(history.watch_id, history.id, conn)? {
+		Some(v) => v,
+		None => return Ok(None)
+	};
+
+	let changes = watcher::get_item_changes(&previous.items, &history.items);
+
+	if changes.is_empty() {
+		return Ok(None);
+	}
+
+	Ok(Some(WatchHistoryChanges {
+		history_id: history.id,
+		watch_id: history.watch_id,
+		changes,
+		diff: history.diff.clone(),
+		date_added: history.date_added
+	}))
+}
+
+
+
 // Request History
 
-pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -> QueryResult<()> {
+/// Returns the inserted group. None if nothing was requested.
+pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -> QueryResult<Option<RequestHistoryGroupModel>> {
 	// Join request batches together (ex. RSS Feed Watcher & Changes)
 	// TODO: Remove tuple.
 	let (req_history_group, items_found) = resp.results.iter()
@@ -921,11 +1350,12 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 		);
 
 	if items_found == 0 {
-		return Ok(());
+		return Ok(None);
 	}
 
 	create_request_history_group(&req_history_group, conn)?;
-	let group_id = get_request_history_group_by_start_time(req_history_group.start_time, conn)?.id;
+	let group = get_request_history_group_by_start_time(req_history_group.start_time, conn)?;
+	let group_id = group.id;
 
 	for res in &resp.results {
 		match res {
@@ -941,7 +1371,16 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 								error: None,
 
 								feed_id: Some(v.item.id),
-								watch_id: None
+								watch_id: None,
+
+								node_count: res.parser_stats.as_ref().map(|v| v.node_count as i32),
+								found_count: res.parser_stats.as_ref().map(|v| v.found_count as i32),
+								field_failures: res.parser_stats.as_ref().map(|v| serde_json::to_string(&v.field_failures).unwrap_or_default()),
+								degraded: res.parser_stats.as_ref().and_then(|v| v.degraded.clone()),
+								fallback_fields: res.parser_stats.as_ref()
+									.filter(|v| !v.fallback_fields.is_empty())
+									.map(|v| serde_json::to_string(&v.fallback_fields).unwrap_or_default()),
+								page_count: res.parser_stats.as_ref().map(|v| v.page_count as i32)
 							},
 							Err(e) => NewRequestHistoryItemModel {
 								group_id,
@@ -951,7 +1390,14 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 								error: Some(e.to_string()),
 
 								feed_id: Some(v.item.id),
-								watch_id: None
+								watch_id: None,
+
+								node_count: None,
+								found_count: None,
+								field_failures: None,
+								degraded: None,
+								fallback_fields: None,
+								page_count: None
 							}
 						}
 					})
@@ -972,7 +1418,16 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 								error: None,
 
 								feed_id: None,
-								watch_id: Some(v.item.id)
+								watch_id: Some(v.item.id),
+
+								node_count: res.parser_stats.as_ref().map(|v| v.node_count as i32),
+								found_count: res.parser_stats.as_ref().map(|v| v.found_count as i32),
+								field_failures: res.parser_stats.as_ref().map(|v| serde_json::to_string(&v.field_failures).unwrap_or_default()),
+								degraded: res.parser_stats.as_ref().and_then(|v| v.degraded.clone()),
+								fallback_fields: res.parser_stats.as_ref()
+									.filter(|v| !v.fallback_fields.is_empty())
+									.map(|v| serde_json::to_string(&v.fallback_fields).unwrap_or_default()),
+								page_count: res.parser_stats.as_ref().map(|v| v.page_count as i32)
 							},
 							Err(e) => NewRequestHistoryItemModel {
 								group_id,
@@ -982,7 +1437,14 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 								error: Some(e.to_string()),
 
 								feed_id: None,
-								watch_id: Some(v.item.id)
+								watch_id: Some(v.item.id),
+
+								node_count: None,
+								found_count: None,
+								field_failures: None,
+								degraded: None,
+								fallback_fields: None,
+								page_count: None
 							}
 						}
 					})
@@ -993,7 +1455,7 @@ pub fn insert_request_history(resp: &RequestResponse, conn: &SqliteConnection) -
 		}
 	}
 
-	Ok(())
+	Ok(Some(group))
 }
 
 
@@ -1034,6 +1496,31 @@ pub fn get_request_history_multiple_group_items(f_group_ids: &[QueryId], conn: &
 	self::request_history_item::table.filter(group_id.eq_any(f_group_ids)).get_results(conn)
 }
 
+/// Latest runs of the feed or watcher which have parser stats. Newest first.
+pub fn get_parser_runs(f_feed_id: Option<QueryId>, f_watch_id: Option<QueryId>, count: i64, conn: &SqliteConnection) -> QueryResult<Vec<RequestHistoryItemModel>> {
+	use self::request_history_item::dsl::*;
+
+	let query = self::request_history_item::table
+		.filter(node_count.is_not_null())
+		.order(id.desc())
+		.limit(count);
+
+	match (f_feed_id, f_watch_id) {
+		(Some(f_feed_id), _) => query.filter(feed_id.eq(f_feed_id)).get_results(conn),
+		(None, Some(f_watch_id)) => query.filter(watch_id.eq(f_watch_id)).get_results(conn),
+		(None, None) => Ok(Vec::new())
+	}
+}
+
+/// Latest run of every feed and watcher whose parser is degraded or relies on fallback selectors.
+pub fn get_degraded_parsers(conn: &SqliteConnection) -> QueryResult<Vec<RequestHistoryItemModel>> {
+	diesel::sql_query(
+		"SELECT * FROM request_history_item WHERE id IN (
+			SELECT MAX(id) FROM request_history_item WHERE node_count IS NOT NULL GROUP BY feed_id, watch_id
+		) AND (degraded IS NOT NULL OR fallback_fields IS NOT NULL)"
+	).load(conn)
+}
+
 pub fn get_request_history_group_items(f_group_id: QueryId, conn: &SqliteConnection) -> QueryResult<Vec<RequestHistoryItemModel>> {
 	use self::request_history_item::dsl::*;
 
@@ -16,6 +16,8 @@ table! {
 		is_removed -> Bool,
 		tags -> Text,
 		feed_id -> Integer,
+
+		date_unparsed -> Bool,
 	}
 }
 
@@ -45,6 +47,8 @@ table! {
 
 		date_added -> BigInt,
 		last_called -> BigInt,
+
+		url_filter -> Nullable<Text>,
 	}
 }
 
@@ -97,6 +101,7 @@ table! {
 		description -> Text,
 
 		search_opts -> Text,
+		priority -> Integer,
 	}
 }
 
@@ -125,6 +130,9 @@ table! {
 
 		date_added -> BigInt,
 		last_called -> BigInt,
+
+		structured_data -> Nullable<Text>,
+		page_text -> Nullable<Text>,
 	}
 }
 
@@ -137,6 +145,8 @@ table! {
 		items -> Text,
 
 		date_added -> BigInt,
+
+		diff -> Nullable<Text>,
 	}
 }
 
@@ -150,6 +160,7 @@ table! {
 		description -> Text,
 
 		match_opts -> Text,
+		priority -> Integer,
 	}
 }
 
@@ -186,5 +197,13 @@ table! {
 
 		// ERROR
 		error -> Nullable<Text>,
+
+		// PARSER
+		node_count -> Nullable<Integer>,
+		found_count -> Nullable<Integer>,
+		field_failures -> Nullable<Text>,
+		degraded -> Nullable<Text>,
+		fallback_fields -> Nullable<Text>,
+		page_count -> Nullable<Integer>,
 	}
 }
\ No newline at end of file
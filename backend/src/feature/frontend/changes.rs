@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use serde::Deserialize;
+use diesel::SqliteConnection;
+use actix_web::{get, web, HttpResponse, ResponseError};
+
+use crate::core::WeakFeederCore;
+use crate::feature::models::{QueryId, WatchingModel};
+use crate::feature::objects::{self, WatchHistoryChanges};
+use crate::request::watcher::FoundItemChange;
+use crate::Result;
+
+
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+	#[serde(default = "default_items")]
+	count: i64
+}
+
+fn default_items() -> i64 {
+	50
+}
+
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+	Atom,
+	Rss
+}
+
+impl Format {
+	fn from_ext(value: &str) -> Option<Self> {
+		match value {
+			"atom" => Some(Format::Atom),
+			"rss" | "xml" => Some(Format::Rss),
+			_ => None
+		}
+	}
+
+	fn content_type(self) -> &'static str {
+		match self {
+			Format::Atom => "application/atom+xml; charset=utf-8",
+			Format::Rss => "application/rss+xml; charset=utf-8"
+		}
+	}
+}
+
+
+/// Every watchers' changes.
+#[get("/feeds/watchers.{format}")]
+pub async fn all_watcher_changes(
+	weak_core: web::Data<WeakFeederCore>,
+	format: web::Path<String>,
+	query: web::Query<ChangesQuery>
+) -> HttpResponse {
+	let format = match Format::from_ext(&format) {
+		Some(v) => v,
+		None => return HttpResponse::NotFound().finish()
+	};
+
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+
+	respond(format, build_feed(format, None, query.count, inner.connection.connection()))
+}
+
+/// A single watchers' changes.
+#[get("/feeds/watchers/{id}.{format}")]
+pub async fn watcher_changes(
+	weak_core: web::Data<WeakFeederCore>,
+	path: web::Path<(QueryId, String)>,
+	query: web::Query<ChangesQuery>
+) -> HttpResponse {
+	let (id, format) = path.into_inner();
+
+	let format = match Format::from_ext(&format) {
+		Some(v) => v,
+		None => return HttpResponse::NotFound().finish()
+	};
+
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+	let conn = inner.connection.connection();
+
+	match objects::get_watcher_by_id(id, conn) {
+		Ok(watcher) => respond(format, build_feed(format, Some(watcher), query.count, conn)),
+		Err(diesel::result::Error::NotFound) => HttpResponse::NotFound().finish(),
+		Err(e) => respond(format, Err(e.into()))
+	}
+}
+
+
+fn respond(format: Format, feed: Result<String>) -> HttpResponse {
+	match feed {
+		Ok(body) => HttpResponse::Ok().content_type(format.content_type()).body(body),
+		Err(e) => {
+			log::error!("Watcher Changes Feed: {}", e);
+			e.error_response()
+		}
+	}
+}
+
+
+/// A single entry in the feed. One per changed item.
+struct ChangeEntry<'a> {
+	id: String,
+	watcher: &'a WatchingModel,
+	change: &'a FoundItemChange,
+	/// Line diff if the watcher watches the page text.
+	diff: Option<&'a str>,
+	date: i64
+}
+
+impl<'a> ChangeEntry<'a> {
+	fn title(&self) -> String {
+		if self.diff.is_some() {
+			return format!("{}: Page text changed", self.watcher.title);
+		}
+
+		let summary = match (self.change.old_value.as_deref(), self.change.new_value.as_deref()) {
+			(Some(old), Some(new)) => format!("{} → {}", old, new),
+			(None, Some(new)) => format!("Added: {}", new),
+			(Some(old), None) => format!("Removed: {}", old),
+			(None, None) => String::new()
+		};
+
+		match self.change.title.as_deref().or(self.change.unique_id.as_deref()) {
+			Some(name) => format!("{} - {}: {}", self.watcher.title, name, summary),
+			None => format!("{}: {}", self.watcher.title, summary)
+		}
+	}
+
+	fn link(&self) -> &str {
+		self.change.link.as_deref().unwrap_or(&self.watcher.url)
+	}
+
+	fn content(&self) -> String {
+		let mut content = format!("<p>Watcher: <a href=\"{}\">{}</a></p>", escape(&self.watcher.url), escape(&self.watcher.title));
+
+		if let Some(title) = self.change.title.as_deref() {
+			match self.change.link.as_deref() {
+				Some(link) => content += &format!("<p>Item: <a href=\"{}\">{}</a></p>", escape(link), escape(title)),
+				None => content += &format!("<p>Item: {}</p>", escape(title))
+			}
+		}
+
+		if let Some(diff) = self.diff {
+			return content + &format!("<pre>{}</pre>", escape(diff));
+		}
+
+		content += &format!(
+			"<p>Old: {}</p><p>New: {}</p>",
+			escape(self.change.old_value.as_deref().unwrap_or("-")),
+			escape(self.change.new_value.as_deref().unwrap_or("-"))
+		);
+
+		content
+	}
+}
+
+
+fn build_feed(format: Format, watcher: Option<WatchingModel>, item_count: i64, conn: &SqliteConnection) -> Result<String> {
+	let history = objects::get_watch_history_changes(watcher.as_ref().map(|w| w.id), item_count, conn)?;
+
+	let watchers: HashMap<QueryId, WatchingModel> = match watcher.as_ref() {
+		Some(w) => vec![(w.id, w.clone())].into_iter().collect(),
+		None => objects::get_watchers(conn)?.into_iter().map(|w| (w.id, w)).collect()
+	};
+
+	let entries = history.iter()
+		.filter_map(|h| Some((watchers.get(&h.watch_id)?, h)))
+		.flat_map(|(watcher, h): (&WatchingModel, &WatchHistoryChanges)| {
+			h.changes.iter()
+				.enumerate()
+				.map(move |(index, change)| ChangeEntry {
+					id: format!("urn:watchmen:watch_history:{}:{}", h.history_id, index),
+					watcher,
+					change,
+					diff: h.diff.as_deref(),
+					date: h.date_added
+				})
+		})
+		.collect::<Vec<_>>();
+
+	let (id, title, link) = match watcher.as_ref() {
+		Some(w) => (format!("urn:watchmen:watcher:{}", w.id), format!("Watchmen - {}", w.title), w.url.clone()),
+		None => ("urn:watchmen:watchers".to_string(), "Watchmen - Watcher Changes".to_string(), String::from("/"))
+	};
+
+	let updated = entries.first().map(|e| e.date).unwrap_or_else(|| Utc::now().timestamp());
+
+	Ok(match format {
+		Format::Atom => {
+			use atom_syndication::{Content, Entry, Feed, Link};
+
+			// Atom writer escapes attributes but not text.
+			let mut feed = Feed::default();
+			feed.set_id(id);
+			feed.set_title(escape(&title));
+			feed.set_updated(to_rfc3339(updated));
+			feed.set_links(vec![create_atom_link(link)]);
+
+			feed.set_entries(
+				entries.iter()
+				.map(|e| {
+					let mut content = Content::default();
+					content.set_content_type(String::from("html"));
+					content.set_value(escape(&e.content()));
+
+					let mut entry = Entry::default();
+					entry.set_id(e.id.clone());
+					entry.set_title(escape(&e.title()));
+					entry.set_updated(to_rfc3339(e.date));
+					entry.set_published(to_rfc3339(e.date));
+					entry.set_links(vec![create_atom_link(e.link().to_string())]);
+					entry.set_content(content);
+					entry
+				})
+				.collect::<Vec<_>>()
+			);
+
+			fn create_atom_link(href: String) -> Link {
+				let mut link = Link::default();
+				link.set_href(href);
+				link
+			}
+
+			feed.to_string()
+		}
+
+		Format::Rss => {
+			use rss::{Channel, Guid, Item};
+
+			let mut channel = Channel::default();
+			channel.set_title(title);
+			channel.set_link(link);
+			channel.set_description(String::from("Changes detected by watchers."));
+			channel.set_last_build_date(to_rfc2822(updated));
+
+			channel.set_items(
+				entries.iter()
+				.map(|e| {
+					let mut guid = Guid::default();
+					guid.set_value(e.id.clone());
+					guid.set_permalink(false);
+
+					let mut item = Item::default();
+					item.set_guid(guid);
+					item.set_title(e.title());
+					item.set_link(e.link().to_string());
+					item.set_description(e.content());
+					item.set_pub_date(to_rfc2822(e.date));
+					item
+				})
+				.collect::<Vec<_>>()
+			);
+
+			channel.to_string()
+		}
+	})
+}
+
+
+fn to_rfc3339(timestamp: i64) -> String {
+	Utc.timestamp_opt(timestamp, 0).unwrap().to_rfc3339()
+}
+
+fn to_rfc2822(timestamp: i64) -> String {
+	Utc.timestamp_opt(timestamp, 0).unwrap().to_rfc2822()
+}
+
+fn escape(value: &str) -> String {
+	value.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use crypto::util::fixed_time_eq;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use diesel::SqliteConnection;
+use actix_web::{route, web, HttpResponse, ResponseError};
+
+use crate::core::WeakFeederCore;
+use crate::feature::models::{FeedItemModel, FeedModel, QueryId};
+use crate::feature::objects;
+use crate::{Error, Result};
+
+
+const API_VERSION: i32 = 3;
+
+/// Fever clients expect at most 50 items per request.
+const MAX_ITEMS: i64 = 50;
+
+type Params = HashMap<String, String>;
+
+
+#[derive(Serialize)]
+struct FeverGroup {
+	id: QueryId,
+	title: String
+}
+
+#[derive(Serialize)]
+struct FeverFeedsGroup {
+	group_id: QueryId,
+	/// Comma separated.
+	feed_ids: String
+}
+
+#[derive(Serialize)]
+struct FeverFeed {
+	id: QueryId,
+	favicon_id: QueryId,
+	title: String,
+	url: String,
+	site_url: String,
+	is_spark: u8,
+	last_updated_on_time: i64
+}
+
+impl From<FeedModel> for FeverFeed {
+	fn from(feed: FeedModel) -> Self {
+		Self {
+			id: feed.id,
+			favicon_id: 0,
+			title: feed.title,
+			site_url: feed.url.clone(),
+			url: feed.url,
+			is_spark: 0,
+			last_updated_on_time: feed.last_called
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct FeverItem {
+	id: QueryId,
+	feed_id: QueryId,
+	title: String,
+	author: String,
+	html: String,
+	url: String,
+	is_saved: u8,
+	is_read: u8,
+	created_on_time: i64
+}
+
+impl From<FeedItemModel> for FeverItem {
+	fn from(item: FeedItemModel) -> Self {
+		Self {
+			id: item.id,
+			feed_id: item.feed_id,
+			title: item.title,
+			author: item.author,
+			html: item.content,
+			url: item.link,
+			is_saved: item.is_starred as u8,
+			is_read: item.is_read as u8,
+			created_on_time: item.date
+		}
+	}
+}
+
+
+/// Fever API. Clients send the api_key in the POST body and what they want in the query (ex. `?api&items&since_id=5`).
+#[route("/fever/", method = "GET", method = "POST")]
+pub async fn fever(
+	weak_core: web::Data<WeakFeederCore>,
+	query: web::Query<Params>,
+	form: Option<web::Form<Params>>
+) -> HttpResponse {
+	let mut params = query.into_inner();
+
+	if let Some(form) = form {
+		params.extend(form.into_inner());
+	}
+
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+
+	let config = inner.get_config().fever;
+
+	if !config.enabled {
+		return HttpResponse::NotFound().finish();
+	}
+
+	let is_authed = !config.api_key.is_empty() && params.get("api_key")
+		.map(|key| fixed_time_eq(key.to_lowercase().as_bytes(), config.api_key.to_lowercase().as_bytes()))
+		.unwrap_or_default();
+
+	let mut resp = Map::new();
+	resp.insert("api_version".into(), json!(API_VERSION));
+	resp.insert("auth".into(), json!(is_authed as u8));
+
+	if !is_authed {
+		return HttpResponse::Ok().json(resp);
+	}
+
+	match handle(&params, &mut resp, inner.connection.connection()) {
+		Ok(_) => HttpResponse::Ok().json(resp),
+		Err(e) => {
+			log::error!("Fever: {}", e);
+			e.error_response()
+		}
+	}
+}
+
+
+fn handle(params: &Params, resp: &mut Map<String, Value>, conn: &SqliteConnection) -> Result<()> {
+	let feeds = objects::get_listeners(conn)?;
+
+	resp.insert("last_refreshed_on_time".into(), json!(feeds.iter().map(|f| f.last_called).max().unwrap_or_default()));
+
+	// Done first so the returned ids are up to date.
+	let marked_as = match params.get("mark") {
+		Some(mark) => Some(mark_as(mark, params, conn)?),
+		None => None
+	};
+
+	if params.contains_key("groups") {
+		let groups = objects::get_categories(conn)?
+			.into_iter()
+			.map(|c| FeverGroup { id: c.id, title: c.name })
+			.collect::<Vec<_>>();
+
+		resp.insert("groups".into(), json!(groups));
+		resp.insert("feeds_groups".into(), json!(get_feeds_groups(conn)?));
+	}
+
+	if params.contains_key("feeds") {
+		resp.insert("feeds".into(), json!(feeds.into_iter().map(FeverFeed::from).collect::<Vec<_>>()));
+		resp.insert("feeds_groups".into(), json!(get_feeds_groups(conn)?));
+	}
+
+	// We don't store either of these.
+	if params.contains_key("favicons") {
+		resp.insert("favicons".into(), json!([]));
+	}
+
+	if params.contains_key("links") {
+		resp.insert("links".into(), json!([]));
+	}
+
+	if params.contains_key("items") {
+		let items = if let Some(ids) = params.get("with_ids") {
+			let ids = ids.split(',')
+				.filter_map(|v| v.trim().parse().ok())
+				.take(MAX_ITEMS as usize)
+				.collect::<Vec<QueryId>>();
+
+			objects::get_items_by_ids(&ids, conn)?
+		} else if let Some(since_id) = get_id(params, "since_id") {
+			objects::get_items_after_id(since_id, MAX_ITEMS, conn)?
+		} else {
+			objects::get_items_before_id(get_id(params, "max_id"), MAX_ITEMS, conn)?
+		};
+
+		resp.insert("total_items".into(), json!(objects::get_item_total(None, None, conn)?));
+		resp.insert("items".into(), json!(items.into_iter().map(FeverItem::from).collect::<Vec<_>>()));
+	}
+
+	if params.contains_key("unread_item_ids") || matches!(marked_as, Some("read" | "unread")) {
+		resp.insert("unread_item_ids".into(), json!(join_ids(&objects::get_unread_item_ids(conn)?)));
+	}
+
+	if params.contains_key("saved_item_ids") || matches!(marked_as, Some("saved" | "unsaved")) {
+		resp.insert("saved_item_ids".into(), json!(join_ids(&objects::get_starred_item_ids(conn)?)));
+	}
+
+	Ok(())
+}
+
+
+/// `mark=item&as=read&id=1`, `mark=feed&as=read&id=1&before=1620000000`. Returns what it was marked as.
+fn mark_as<'a>(mark: &str, params: &'a Params, conn: &SqliteConnection) -> Result<&'a str> {
+	let id = get_id(params, "id").ok_or_else(|| Error::MissingField("id".into()))?;
+	let marking = params.get("as").map(|v| v.as_str()).ok_or_else(|| Error::MissingField("as".into()))?;
+
+	let before = params.get("before")
+		.and_then(|v| v.parse().ok())
+		.unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+	match (mark, marking) {
+		("item", "read") => objects::update_item_read(id, true, conn)?,
+		("item", "unread") => objects::update_item_read(id, false, conn)?,
+		("item", "saved") => objects::update_item_starred(id, true, conn)?,
+		("item", "unsaved") => objects::update_item_starred(id, false, conn)?,
+
+		("feed", "read") => objects::mark_items_read_before(Some(&[id]), before, conn)?,
+
+		// Group 0 is every feed.
+		("group", "read") if id == 0 => objects::mark_items_read_before(None, before, conn)?,
+		("group", "read") => {
+			let feed_ids = objects::get_category_feeds(id, conn)?
+				.into_iter()
+				.map(|v| v.feed_id)
+				.collect::<Vec<_>>();
+
+			objects::mark_items_read_before(Some(&feed_ids), before, conn)?
+		}
+
+		_ => return Err(Error::InvalidField("mark".into(), format!("Unknown Fever mark: {} as {}", mark, marking)))
+	};
+
+	Ok(marking)
+}
+
+
+fn get_feeds_groups(conn: &SqliteConnection) -> Result<Vec<FeverFeedsGroup>> {
+	let mut groups: HashMap<QueryId, Vec<QueryId>> = HashMap::new();
+
+	for feed_cat in objects::get_feed_categories(conn)? {
+		groups.entry(feed_cat.category_id).or_default().push(feed_cat.feed_id);
+	}
+
+	Ok(
+		groups.into_iter()
+		.map(|(group_id, feed_ids)| FeverFeedsGroup {
+			group_id,
+			feed_ids: join_ids(&feed_ids)
+		})
+		.collect()
+	)
+}
+
+fn get_id(params: &Params, name: &str) -> Option<QueryId> {
+	params.get(name).and_then(|v| v.parse().ok())
+}
+
+fn join_ids(ids: &[QueryId]) -> String {
+	ids.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+}
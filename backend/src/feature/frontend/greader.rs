@@ -0,0 +1,849 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use crypto::util::fixed_time_eq;
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use diesel::SqliteConnection;
+use actix_web::{route, web, HttpRequest, HttpResponse, ResponseError, Scope};
+use url::form_urlencoded;
+
+use crate::config::ConfigGReader;
+use crate::core::WeakFeederCore;
+use crate::feature::{Front2CoreNotification, ResponseWrapper};
+use crate::feature::models::{EditCategoryModel, EditFeedModel, FeedItemModel, FeedModel, NewFeedCategoryModel, QueryId};
+use crate::feature::objects::{self, ItemFilter};
+use crate::types::MessageId;
+use crate::{Error, Result};
+
+
+const MAX_ITEMS: i64 = 1000;
+const DEFAULT_ITEMS: i64 = 20;
+
+const ITEM_ID_PREFIX: &str = "tag:google.com,2005:reader/item/";
+
+const READING_LIST: &str = "user/-/state/com.google/reading-list";
+
+
+lazy_static! {
+	/// Signs auth tokens. New every start so tokens from before a restart stop working.
+	static ref TOKEN_SECRET: [u8; 32] = rand::random();
+}
+
+
+/// Google Reader API. Used by most mobile RSS apps. Everything is under `/greader`.
+pub fn scope() -> Scope {
+	web::scope("/greader")
+		.service(client_login)
+		.service(token)
+		.service(user_info)
+		.service(subscription_list)
+		.service(subscription_edit)
+		.service(subscription_quickadd)
+		.service(tag_list)
+		.service(rename_tag)
+		.service(disable_tag)
+		.service(unread_count)
+		.service(stream_item_ids)
+		.service(stream_item_contents)
+		.service(stream_contents)
+		.service(edit_tag)
+		.service(mark_all_as_read)
+}
+
+
+/// Query and form values. Some are repeated (ex. `i=1&i=2`).
+struct Params(Vec<(String, String)>);
+
+impl Params {
+	fn new(req: &HttpRequest, body: &[u8]) -> Self {
+		let mut pairs = form_urlencoded::parse(req.query_string().as_bytes()).into_owned().collect::<Vec<_>>();
+		pairs.extend(form_urlencoded::parse(body).into_owned());
+
+		Self(pairs)
+	}
+
+	fn get(&self, name: &str) -> Option<&str> {
+		self.0.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.0.iter()
+			.filter(move |(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+
+	fn get_parsed<T: FromStr>(&self, name: &str) -> Option<T> {
+		self.get(name).and_then(|v| v.parse().ok())
+	}
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+enum StreamId {
+	ReadingList,
+	Starred,
+	Read,
+	KeptUnread,
+	/// Feed ID, or the URL when subscribing.
+	Feed(String),
+	/// Category name or item label.
+	Label(String)
+}
+
+impl StreamId {
+	fn parse(value: &str) -> Option<Self> {
+		if let Some(feed) = value.strip_prefix("feed/") {
+			return Some(StreamId::Feed(feed.to_string()));
+		}
+
+		// "user/-/..." or "user/{user id}/..."
+		let (_, path) = value.strip_prefix("user/")?.split_once('/')?;
+
+		match path {
+			"state/com.google/reading-list" => Some(StreamId::ReadingList),
+			"state/com.google/starred" => Some(StreamId::Starred),
+			"state/com.google/read" => Some(StreamId::Read),
+			"state/com.google/kept-unread" => Some(StreamId::KeptUnread),
+			_ => path.strip_prefix("label/").map(|v| StreamId::Label(v.to_string()))
+		}
+	}
+
+	fn feed_id(&self) -> Option<QueryId> {
+		match self {
+			StreamId::Feed(id) => id.parse().ok(),
+			_ => None
+		}
+	}
+}
+
+
+struct IgnoreResponse;
+
+impl ResponseWrapper for IgnoreResponse {
+	fn respond(&mut self, _: Option<MessageId>, _: Result<Value>) {}
+}
+
+
+/// Feeds and the category names they're in.
+struct Lookup {
+	feeds: HashMap<QueryId, FeedModel>,
+	feed_labels: HashMap<QueryId, Vec<String>>
+}
+
+impl Lookup {
+	fn load(conn: &SqliteConnection) -> Result<Self> {
+		let categories = objects::get_categories(conn)?
+			.into_iter()
+			.map(|c| (c.id, c.name))
+			.collect::<HashMap<_, _>>();
+
+		let mut feed_labels: HashMap<QueryId, Vec<String>> = HashMap::new();
+
+		for feed_cat in objects::get_feed_categories(conn)? {
+			if let Some(name) = categories.get(&feed_cat.category_id) {
+				feed_labels.entry(feed_cat.feed_id).or_default().push(name.clone());
+			}
+		}
+
+		Ok(Self {
+			feeds: objects::get_listeners(conn)?.into_iter().map(|f| (f.id, f)).collect(),
+			feed_labels
+		})
+	}
+
+	fn labels(&self, feed_id: QueryId) -> &[String] {
+		self.feed_labels.get(&feed_id).map(|v| v.as_slice()).unwrap_or_default()
+	}
+
+	fn item_json(&self, item: FeedItemModel) -> Value {
+		let mut categories = vec![READING_LIST.to_string()];
+
+		if item.is_read {
+			categories.push("user/-/state/com.google/read".into());
+		}
+
+		if item.is_starred {
+			categories.push("user/-/state/com.google/starred".into());
+		}
+
+		categories.extend(
+			self.labels(item.feed_id).iter()
+				.cloned()
+				.chain(objects::get_item_labels(&item))
+				.map(|v| format!("user/-/label/{}", v))
+		);
+
+		let feed = self.feeds.get(&item.feed_id);
+
+		json!({
+			"id": long_item_id(item.id),
+			"crawlTimeMsec": (item.date_added * 1000).to_string(),
+			"timestampUsec": (item.date_added * 1_000_000).to_string(),
+			"published": item.date,
+			"updated": item.date,
+			"title": item.title,
+			"canonical": [{ "href": item.link }],
+			"alternate": [{ "href": item.link, "type": "text/html" }],
+			"summary": {
+				"direction": "ltr",
+				"content": item.content
+			},
+			"author": item.author,
+			"categories": categories,
+			"origin": {
+				"streamId": format!("feed/{}", item.feed_id),
+				"title": feed.map(|f| f.title.as_str()).unwrap_or_default(),
+				"htmlUrl": feed.map(|f| f.url.as_str()).unwrap_or_default()
+			}
+		})
+	}
+}
+
+
+// Auth
+
+/// Changing the username or password also changes the token.
+fn auth_token(config: &ConfigGReader) -> String {
+	let mut hmac = Hmac::new(Sha256::new(), &TOKEN_SECRET[..]);
+	hmac.input(format!("{}:{}", config.username, config.password).as_bytes());
+
+	let signature = hmac.result().code().iter().map(|v| format!("{:02x}", v)).collect::<String>();
+
+	format!("{}/{}", config.username, signature)
+}
+
+/// Returns the response to send instead if it's disabled or unauthorized.
+fn check_access(config: &ConfigGReader, req: &HttpRequest) -> Option<HttpResponse> {
+	if !config.enabled {
+		return Some(HttpResponse::NotFound().finish());
+	}
+
+	let is_authed = !config.username.is_empty() && req.headers()
+		.get("Authorization")
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| v.strip_prefix("GoogleLogin auth="))
+		.map(|v| fixed_time_eq(v.trim().as_bytes(), auth_token(config).as_bytes()))
+		.unwrap_or_default();
+
+	if is_authed {
+		None
+	} else {
+		Some(HttpResponse::Unauthorized().body("Unauthorized"))
+	}
+}
+
+fn get_config(weak_core: &WeakFeederCore) -> ConfigGReader {
+	weak_core.upgrade().unwrap().to_inner().get_config().greader
+}
+
+/// Checks access then calls `func` with the database connection.
+fn run<F: FnOnce(&SqliteConnection) -> Result<HttpResponse>>(weak_core: &WeakFeederCore, req: &HttpRequest, func: F) -> HttpResponse {
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+
+	if let Some(resp) = check_access(&inner.get_config().greader, req) {
+		return resp;
+	}
+
+	func(inner.connection.connection()).unwrap_or_else(error_response)
+}
+
+fn error_response(error: Error) -> HttpResponse {
+	log::error!("GReader: {}", error);
+	error.error_response()
+}
+
+fn ok() -> HttpResponse {
+	HttpResponse::Ok().content_type("text/plain").body("OK")
+}
+
+
+#[route("/accounts/ClientLogin", method = "GET", method = "POST")]
+pub async fn client_login(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+	let config = get_config(&weak_core);
+
+	if !config.enabled {
+		return HttpResponse::NotFound().finish();
+	}
+
+	let is_valid = !config.username.is_empty()
+		&& params.get("Email") == Some(config.username.as_str())
+		&& params.get("Passwd").map(|v| fixed_time_eq(v.as_bytes(), config.password.as_bytes())).unwrap_or_default();
+
+	if !is_valid {
+		return HttpResponse::Unauthorized().content_type("text/plain").body("Error=BadAuthentication\n");
+	}
+
+	HttpResponse::Ok()
+		.content_type("text/plain")
+		.body(format!("SID={0}\nLSID={0}\nAuth={0}\n", auth_token(&config)))
+}
+
+#[route("/reader/api/0/token", method = "GET", method = "POST")]
+pub async fn token(weak_core: web::Data<WeakFeederCore>, req: HttpRequest) -> HttpResponse {
+	let config = get_config(&weak_core);
+
+	check_access(&config, &req)
+		.unwrap_or_else(|| HttpResponse::Ok().content_type("text/plain").body(auth_token(&config)))
+}
+
+#[route("/reader/api/0/user-info", method = "GET")]
+pub async fn user_info(weak_core: web::Data<WeakFeederCore>, req: HttpRequest) -> HttpResponse {
+	let config = get_config(&weak_core);
+
+	check_access(&config, &req)
+		.unwrap_or_else(|| HttpResponse::Ok().json(json!({
+			"userId": "1",
+			"userName": config.username,
+			"userProfileId": "1",
+			"userEmail": config.username
+		})))
+}
+
+
+// Subscriptions
+
+#[route("/reader/api/0/subscription/list", method = "GET")]
+pub async fn subscription_list(weak_core: web::Data<WeakFeederCore>, req: HttpRequest) -> HttpResponse {
+	run(&weak_core, &req, |conn| {
+		let lookup = Lookup::load(conn)?;
+
+		let mut feeds = lookup.feeds.values().collect::<Vec<_>>();
+		feeds.sort_by_key(|f| f.id);
+
+		let subscriptions = feeds.into_iter()
+			.map(|feed| json!({
+				"id": format!("feed/{}", feed.id),
+				"title": feed.title,
+				"categories": lookup.labels(feed.id).iter()
+					.map(|name| json!({ "id": format!("user/-/label/{}", name), "label": name }))
+					.collect::<Vec<_>>(),
+				"url": feed.url,
+				"htmlUrl": feed.url,
+				"iconUrl": ""
+			}))
+			.collect::<Vec<_>>();
+
+		Ok(HttpResponse::Ok().json(json!({ "subscriptions": subscriptions })))
+	})
+}
+
+/// `ac=subscribe|unsubscribe|edit`, `s` stream, `t` title, `a` label to add, `r` label to remove.
+#[route("/reader/api/0/subscription/edit", method = "POST")]
+pub async fn subscription_edit(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	if let Some(resp) = check_access(&get_config(&weak_core), &req) {
+		return resp;
+	}
+
+	let mut feed_ids = Vec::new();
+
+	for stream in params.get_all("s").filter_map(StreamId::parse) {
+		match (stream.feed_id(), stream) {
+			(Some(id), _) => feed_ids.push(id),
+
+			(None, StreamId::Feed(url)) if params.get("ac") == Some("subscribe") => {
+				match subscribe(&weak_core, &url).await {
+					Ok(feed) => feed_ids.push(feed.id),
+					Err(e) => return error_response(e)
+				}
+			}
+
+			_ => ()
+		}
+	}
+
+	run(&weak_core, &req, |conn| {
+		edit_subscriptions(&params, &feed_ids, conn)?;
+
+		Ok(ok())
+	})
+}
+
+/// `quickadd` is the feed URL.
+#[route("/reader/api/0/subscription/quickadd", method = "POST")]
+pub async fn subscription_quickadd(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	if let Some(resp) = check_access(&get_config(&weak_core), &req) {
+		return resp;
+	}
+
+	let url = match params.get("quickadd") {
+		Some(url) => url.strip_prefix("feed/").unwrap_or(url),
+		None => return error_response(Error::MissingField("quickadd".into()))
+	};
+
+	match subscribe(&weak_core, url).await {
+		Ok(feed) => HttpResponse::Ok().json(json!({
+			"numResults": 1,
+			"query": url,
+			"streamId": format!("feed/{}", feed.id),
+			"streamName": feed.title
+		})),
+
+		Err(e) => error_response(e)
+	}
+}
+
+/// Adds the feed if it doesn't exist yet.
+async fn subscribe(weak_core: &WeakFeederCore, url: &str) -> Result<FeedModel> {
+	{
+		let core = weak_core.upgrade().unwrap();
+		let inner = core.to_inner();
+
+		if let Some(feed) = objects::get_listener_by_url(url, inner.connection.connection())? {
+			return Ok(feed);
+		}
+	}
+
+	weak_core.handle_response(
+		&mut IgnoreResponse,
+		None,
+		Front2CoreNotification::AddListener {
+			url: url.to_string(),
+			custom_item_id: None,
+			url_filter: None
+		}
+	).await?;
+
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+
+	objects::get_listener_by_url(url, inner.connection.connection())?
+		.ok_or_else(|| Error::Other(format!("Unable to find \"{}\" after adding it.", url)))
+}
+
+fn edit_subscriptions(params: &Params, feed_ids: &[QueryId], conn: &SqliteConnection) -> Result<()> {
+	if params.get("ac") == Some("unsubscribe") {
+		for &id in feed_ids {
+			objects::remove_listener(id, true, conn)?;
+		}
+
+		return Ok(());
+	}
+
+	if let Some(title) = params.get("t").filter(|v| !v.is_empty()) {
+		let edit = EditFeedModel {
+			title: Some(title.to_string()),
+			.. EditFeedModel::default()
+		};
+
+		for &id in feed_ids {
+			objects::update_listener(id, &edit, conn)?;
+		}
+	}
+
+	let feed_cats = objects::get_feed_categories(conn)?;
+
+	for stream in params.get_all("a").filter_map(StreamId::parse) {
+		if let StreamId::Label(name) = stream {
+			let category = objects::get_or_create_category(&name, conn)?;
+
+			for &feed_id in feed_ids {
+				if !feed_cats.iter().any(|v| v.feed_id == feed_id && v.category_id == category.id) {
+					objects::create_category_feed(&NewFeedCategoryModel { feed_id, category_id: category.id }, conn)?;
+				}
+			}
+		}
+	}
+
+	for stream in params.get_all("r").filter_map(StreamId::parse) {
+		if let StreamId::Label(name) = stream {
+			if let Some(category) = objects::get_category_by_name(&name, conn)? {
+				for feed_cat in feed_cats.iter().filter(|v| v.category_id == category.id && feed_ids.contains(&v.feed_id)) {
+					objects::remove_category_feed(feed_cat.id, conn)?;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+
+// Tags
+
+#[route("/reader/api/0/tag/list", method = "GET")]
+pub async fn tag_list(weak_core: web::Data<WeakFeederCore>, req: HttpRequest) -> HttpResponse {
+	run(&weak_core, &req, |conn| {
+		let mut tags = vec![json!({ "id": "user/-/state/com.google/starred" })];
+
+		tags.extend(
+			objects::get_categories(conn)?
+				.into_iter()
+				.map(|c| json!({ "id": format!("user/-/label/{}", c.name), "type": "folder" }))
+		);
+
+		Ok(HttpResponse::Ok().json(json!({ "tags": tags })))
+	})
+}
+
+/// `s` label to rename, `dest` new label.
+#[route("/reader/api/0/rename-tag", method = "POST")]
+pub async fn rename_tag(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		let from = params.get("s").and_then(StreamId::parse);
+		let dest = params.get("dest").and_then(StreamId::parse);
+
+		if let (Some(StreamId::Label(from)), Some(StreamId::Label(dest))) = (from, dest) {
+			if let Some(category) = objects::get_category_by_name(&from, conn)? {
+				objects::update_category(
+					category.id,
+					&EditCategoryModel {
+						position: None,
+						name_lowercase: Some(dest.to_lowercase()),
+						name: Some(dest),
+						date_added: None
+					},
+					conn
+				)?;
+			}
+		}
+
+		Ok(ok())
+	})
+}
+
+/// Removes the category. Feeds in it are kept.
+#[route("/reader/api/0/disable-tag", method = "POST")]
+pub async fn disable_tag(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		if let Some(StreamId::Label(name)) = params.get("s").and_then(StreamId::parse) {
+			if let Some(category) = objects::get_category_by_name(&name, conn)? {
+				for feed_cat in objects::get_category_feeds(category.id, conn)? {
+					objects::remove_category_feed(feed_cat.id, conn)?;
+				}
+
+				objects::remove_category(category.id, conn)?;
+			}
+		}
+
+		Ok(ok())
+	})
+}
+
+/// `i` item (repeated), `a` tag to add, `r` tag to remove.
+#[route("/reader/api/0/edit-tag", method = "POST")]
+pub async fn edit_tag(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		let ids = params.get_all("i").filter_map(parse_item_id).collect::<Vec<_>>();
+
+		let add = params.get_all("a").filter_map(StreamId::parse).collect::<Vec<_>>();
+		let remove = params.get_all("r").filter_map(StreamId::parse).collect::<Vec<_>>();
+
+		for (tags, value) in [(&add, true), (&remove, false)] {
+			for tag in tags {
+				match tag {
+					StreamId::Read => { objects::update_items_read(&ids, value, conn)?; }
+					StreamId::KeptUnread => { objects::update_items_read(&ids, !value, conn)?; }
+					StreamId::Starred => {
+						for &id in &ids {
+							objects::update_item_starred(id, value, conn)?;
+						}
+					}
+
+					_ => ()
+				}
+			}
+		}
+
+		let add_labels = labels_of(&add);
+		let remove_labels = labels_of(&remove);
+
+		if !add_labels.is_empty() || !remove_labels.is_empty() {
+			for item in objects::get_items_by_ids(&ids, conn)? {
+				let current = objects::get_item_labels(&item);
+
+				let mut labels = current.iter()
+					.filter(|v| !remove_labels.contains(v))
+					.cloned()
+					.collect::<Vec<_>>();
+
+				for label in &add_labels {
+					if !labels.contains(label) {
+						labels.push(label.to_string());
+					}
+				}
+
+				if labels != current {
+					objects::update_item_labels(item.id, &labels, conn)?;
+				}
+			}
+		}
+
+		Ok(ok())
+	})
+}
+
+/// `s` stream, `ts` only items older than it (microseconds).
+#[route("/reader/api/0/mark-all-as-read", method = "POST")]
+pub async fn mark_all_as_read(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		let stream = match params.get("s").and_then(StreamId::parse) {
+			Some(v) => v,
+			None => return Ok(HttpResponse::BadRequest().body("Unknown stream"))
+		};
+
+		let before = params.get_parsed::<i64>("ts")
+			.map(|v| v / 1_000_000)
+			.unwrap_or_else(|| chrono::Utc::now().timestamp());
+
+		let mut filter = stream_filter(&stream, conn)?;
+		filter.is_read = Some(false);
+		filter.older_than = Some(before + 1);
+
+		objects::mark_filtered_items_read(&filter, conn)?;
+
+		Ok(ok())
+	})
+}
+
+
+// Streams
+
+#[route("/reader/api/0/unread-count", method = "GET")]
+pub async fn unread_count(weak_core: web::Data<WeakFeederCore>, req: HttpRequest) -> HttpResponse {
+	run(&weak_core, &req, |conn| {
+		let lookup = Lookup::load(conn)?;
+
+		// Stream ID -> (Count, Newest)
+		let mut counts: HashMap<String, (i64, i64)> = HashMap::new();
+
+		for (feed_id, date_added) in objects::get_unread_item_dates(conn)? {
+			let streams = std::iter::once(READING_LIST.to_string())
+				.chain(std::iter::once(format!("feed/{}", feed_id)))
+				.chain(lookup.labels(feed_id).iter().map(|v| format!("user/-/label/{}", v)));
+
+			for stream in streams {
+				let (count, newest) = counts.entry(stream).or_default();
+				*count += 1;
+				*newest = date_added.max(*newest);
+			}
+		}
+
+		let unread_counts = counts.into_iter()
+			.map(|(id, (count, newest))| json!({
+				"id": id,
+				"count": count,
+				"newestItemTimestampUsec": (newest * 1_000_000).to_string()
+			}))
+			.collect::<Vec<_>>();
+
+		Ok(HttpResponse::Ok().json(json!({
+			"max": MAX_ITEMS,
+			"unreadcounts": unread_counts
+		})))
+	})
+}
+
+#[route("/reader/api/0/stream/items/ids", method = "GET", method = "POST")]
+pub async fn stream_item_ids(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		let stream = match params.get("s").and_then(StreamId::parse) {
+			Some(v) => v,
+			None => return Ok(HttpResponse::BadRequest().body("Unknown stream"))
+		};
+
+		let item_count = get_item_count(&params);
+
+		let items = objects::get_filtered_items(&params_filter(stream_filter(&stream, conn)?, &params), item_count, conn)?;
+
+		let mut resp = json!({
+			"itemRefs": items.iter()
+				.map(|item| json!({
+					"id": item.id.to_string(),
+					"directStreamIds": [format!("feed/{}", item.feed_id)],
+					"timestampUsec": (item.date_added * 1_000_000).to_string()
+				}))
+				.collect::<Vec<_>>()
+		});
+
+		if let Some(last) = items.last().filter(|_| items.len() as i64 == item_count) {
+			resp["continuation"] = json!(continuation(last));
+		}
+
+		Ok(HttpResponse::Ok().json(resp))
+	})
+}
+
+/// `i` item (repeated).
+#[route("/reader/api/0/stream/items/contents", method = "GET", method = "POST")]
+pub async fn stream_item_contents(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes) -> HttpResponse {
+	let params = Params::new(&req, &body);
+
+	run(&weak_core, &req, |conn| {
+		let lookup = Lookup::load(conn)?;
+
+		let ids = params.get_all("i")
+			.filter_map(parse_item_id)
+			.take(MAX_ITEMS as usize)
+			.collect::<Vec<_>>();
+
+		let items = objects::get_items_by_ids(&ids, conn)?;
+
+		Ok(HttpResponse::Ok().json(json!({
+			"direction": "ltr",
+			"id": READING_LIST,
+			"updated": chrono::Utc::now().timestamp(),
+			"items": items.into_iter().map(|v| lookup.item_json(v)).collect::<Vec<_>>()
+		})))
+	})
+}
+
+#[route("/reader/api/0/stream/contents/{stream:.*}", method = "GET", method = "POST")]
+pub async fn stream_contents(weak_core: web::Data<WeakFeederCore>, req: HttpRequest, body: web::Bytes, path: web::Path<String>) -> HttpResponse {
+	let params = Params::new(&req, &body);
+	let path = path.into_inner();
+
+	run(&weak_core, &req, |conn| {
+		let stream_id = if path.is_empty() {
+			params.get("s").unwrap_or(READING_LIST)
+		} else {
+			path.as_str()
+		};
+
+		let stream = match StreamId::parse(stream_id) {
+			Some(v) => v,
+			None => return Ok(HttpResponse::BadRequest().body("Unknown stream"))
+		};
+
+		let lookup = Lookup::load(conn)?;
+
+		let title = match &stream {
+			StreamId::Feed(_) => stream.feed_id()
+				.and_then(|id| lookup.feeds.get(&id))
+				.map(|f| f.title.clone())
+				.unwrap_or_default(),
+			StreamId::Label(name) => name.clone(),
+			StreamId::Starred => String::from("Starred"),
+			_ => String::from("Reading List")
+		};
+
+		let item_count = get_item_count(&params);
+
+		let items = objects::get_filtered_items(&params_filter(stream_filter(&stream, conn)?, &params), item_count, conn)?;
+
+		let mut resp = json!({
+			"direction": "ltr",
+			"id": stream_id,
+			"title": title,
+			"updated": chrono::Utc::now().timestamp()
+		});
+
+		if let Some(last) = items.last().filter(|_| items.len() as i64 == item_count) {
+			resp["continuation"] = json!(continuation(last));
+		}
+
+		resp["items"] = json!(items.into_iter().map(|v| lookup.item_json(v)).collect::<Vec<_>>());
+
+		Ok(HttpResponse::Ok().json(resp))
+	})
+}
+
+
+fn stream_filter(stream: &StreamId, conn: &SqliteConnection) -> Result<ItemFilter> {
+	let mut filter = ItemFilter::default();
+
+	match stream {
+		StreamId::ReadingList => (),
+		StreamId::Starred => filter.is_starred = Some(true),
+		StreamId::Read => filter.is_read = Some(true),
+		StreamId::KeptUnread => filter.is_read = Some(false),
+
+		StreamId::Feed(_) => filter.feed_ids = Some(stream.feed_id().into_iter().collect()),
+
+		// Feeds in the category and items with the label.
+		StreamId::Label(name) => {
+			if let Some(category) = objects::get_category_by_name(name, conn)? {
+				filter.feed_ids = Some(objects::get_category_feeds(category.id, conn)?.into_iter().map(|v| v.feed_id).collect());
+			}
+
+			filter.label = Some(name.clone());
+		}
+	}
+
+	Ok(filter)
+}
+
+/// `xt` exclude, `it` include, `ot` older than, `nt` newer than, `r=o` oldest first.
+fn params_filter(mut filter: ItemFilter, params: &Params) -> ItemFilter {
+	if params.get_all("xt").filter_map(StreamId::parse).any(|v| v == StreamId::Read) {
+		filter.is_read = Some(false);
+	}
+
+	for stream in params.get_all("it").filter_map(StreamId::parse) {
+		match stream {
+			StreamId::Read => filter.is_read = Some(true),
+			StreamId::Starred => filter.is_starred = Some(true),
+			_ => ()
+		}
+	}
+
+	if let Some(value) = params.get_parsed("ot") {
+		filter.older_than = Some(value);
+	}
+
+	if let Some(value) = params.get_parsed("nt") {
+		filter.newer_than = Some(value);
+	}
+
+	filter.oldest_first = params.get("r") == Some("o");
+
+	// `c` continuation. "{date_added}_{id}" of the last item on the previous page.
+	filter.after = params.get("c")
+		.and_then(|v| v.split_once('_'))
+		.and_then(|(date, id)| Some((date.parse().ok()?, id.parse().ok()?)));
+
+	filter
+}
+
+/// `n` count.
+fn get_item_count(params: &Params) -> i64 {
+	params.get_parsed("n").unwrap_or(DEFAULT_ITEMS).clamp(1, MAX_ITEMS)
+}
+
+fn continuation(item: &FeedItemModel) -> String {
+	format!("{}_{}", item.date_added, item.id)
+}
+
+fn labels_of(streams: &[StreamId]) -> Vec<String> {
+	streams.iter()
+		.filter_map(|v| match v {
+			StreamId::Label(name) => Some(name.clone()),
+			_ => None
+		})
+		.collect()
+}
+
+
+fn long_item_id(id: QueryId) -> String {
+	format!("{}{:016x}", ITEM_ID_PREFIX, id)
+}
+
+/// Long form (`tag:google.com,2005:reader/item/000000000000001a`) or short (`26`).
+fn parse_item_id(value: &str) -> Option<QueryId> {
+	match value.strip_prefix(ITEM_ID_PREFIX) {
+		Some(hex) => i64::from_str_radix(hex, 16).ok(),
+		None => value.parse::<i64>().ok()
+	}
+	.and_then(|v| QueryId::try_from(v).ok())
+}
@@ -4,6 +4,9 @@ use crate::core::WeakFeederCore;
 
 pub mod web;
 pub mod socket;
+pub mod changes;
+pub mod fever;
+pub mod greader;
 
 pub use web::Web;
 pub use socket::WebsocketWrapper;
@@ -1,6 +1,8 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Mutex;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
+use diesel::SqliteConnection;
 use lazy_static::lazy_static;
 use log;
 
@@ -11,9 +13,13 @@ use actix_web::{web, Error as ActixError, HttpRequest, HttpResponse};
 use actix_web_actors::ws::{self, WebsocketContext};
 
 use crate::rpc::Object2CoreNotification;
-use crate::feature::{ResponseWrapper, Core2FrontNotification};
+use crate::feature::{ResponseWrapper, Core2FrontNotification, Front2CoreNotification};
+use crate::feature::models::{FeedItemModel, QueryId, RequestHistoryGroupModel, RequestHistoryItemModel};
+use crate::feature::objects::{self, WatchHistoryChanges};
+use crate::feature::rpc::{RequestError, SocketTopic};
 use crate::core::WeakFeederCore;
 use crate::error::Error;
+use crate::request::{RequestItemResults, RequestResults};
 use crate::state::RequestResponse;
 use crate::types::MessageId;
 
@@ -23,17 +29,23 @@ const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
 
 
 lazy_static! {
-	pub static ref SOCKET_CLIENTS: Mutex<Vec<Recipient<Line>>> = Mutex::new(Vec::new());
+	pub static ref SOCKET_CLIENTS: Mutex<Vec<SocketClient>> = Mutex::new(Vec::new());
 }
 
 
-pub fn send_req_resp_to_clients(resp: &RequestResponse) -> crate::Result<()> {
+pub struct SocketClient {
+	pub recipient: Recipient<Line>,
+	pub topics: HashSet<SocketTopic>
+}
+
+
+pub fn send_req_resp_to_clients(resp: &RequestResponse, history_group: Option<RequestHistoryGroupModel>, conn: &SqliteConnection) -> crate::Result<()> {
 	let mut watch_items_count = 0;
 	let mut feed_items_count = 0;
 
 	resp.results.iter()
 	.for_each(|r| match r {
-		crate::request::RequestResults::Feed(v) => {
+		RequestResults::Feed(v) => {
 			for item in v.items.iter() {
 				if let Ok(res) = &item.results {
 					feed_items_count += res.new_item_count;
@@ -41,7 +53,7 @@ pub fn send_req_resp_to_clients(resp: &RequestResponse) -> crate::Result<()> {
 			}
 		}
 
-		crate::request::RequestResults::Watcher(v) => {
+		RequestResults::Watcher(v) => {
 			for item in v.items.iter() {
 				if let Ok(res) = &item.results {
 					watch_items_count += res.new_item_count;
@@ -56,16 +68,220 @@ pub fn send_req_resp_to_clients(resp: &RequestResponse) -> crate::Result<()> {
 		feed_items_count
 	};
 
+	// Every subscribed topic. Used to only query what's needed.
+	let topics = {
+		let lock = SOCKET_CLIENTS.lock().unwrap();
+
+		for client in lock.iter() {
+			WebsocketWrapper::new(&client.recipient).respond_with(None, response.clone());
+		}
+
+		lock.iter().flat_map(|c| c.topics.iter().cloned()).collect::<HashSet<_>>()
+	};
+
+	if topics.is_empty() {
+		return Ok(());
+	}
+
+	let updates = CycleUpdates::collect(resp, history_group, &topics, conn)?;
+
 	let lock = SOCKET_CLIENTS.lock().unwrap();
 
-	for recipient in lock.iter() {
-		WebsocketWrapper::new(recipient).respond_with(None, response.clone());
+	for client in lock.iter() {
+		updates.send_to(client);
 	}
 
 	Ok(())
 }
 
 
+/// What changed during a request cycle. Only filled in if a client is subscribed to it.
+#[derive(Default)]
+struct CycleUpdates {
+	items: Vec<FeedItemModel>,
+	/// Feed ID -> Category IDs
+	feed_categories: HashMap<QueryId, Vec<QueryId>>,
+
+	watcher_changes: Vec<WatchHistoryChanges>,
+
+	history: Option<(RequestHistoryGroupModel, Vec<RequestHistoryItemModel>)>,
+
+	errors: Vec<RequestError>
+}
+
+impl CycleUpdates {
+	fn collect(
+		resp: &RequestResponse,
+		history_group: Option<RequestHistoryGroupModel>,
+		topics: &HashSet<SocketTopic>,
+		conn: &SqliteConnection
+	) -> crate::Result<Self> {
+		let mut updates = Self::default();
+
+		let wants_items = topics.iter().any(|t| matches!(t, SocketTopic::Items | SocketTopic::Category(_) | SocketTopic::Feed(_)));
+		let wants_changes = topics.iter().any(|t| matches!(t, SocketTopic::Watchers | SocketTopic::Watcher(_)));
+		let wants_errors = topics.contains(&SocketTopic::Errors);
+
+		let mut feed_ids = Vec::new();
+
+		for result in &resp.results {
+			match result {
+				RequestResults::Feed(v) => {
+					if let Some(error) = v.general_error.as_ref().filter(|_| wants_errors) {
+						updates.errors.push(RequestError { feed_id: None, watch_id: None, title: None, error: error.clone() });
+					}
+
+					for item in &v.items {
+						match &item.results {
+							Ok(res) => {
+								if wants_items && res.new_item_count != 0 {
+									feed_ids.push(item.item.id);
+								}
+
+								if let Some(reason) = degraded_reason(res).filter(|_| wants_errors) {
+									updates.errors.push(RequestError {
+										feed_id: Some(item.item.id),
+										watch_id: None,
+										title: Some(item.item.title.clone()),
+										error: reason
+									});
+								}
+							}
+
+							Err(e) => if wants_errors {
+								updates.errors.push(RequestError {
+									feed_id: Some(item.item.id),
+									watch_id: None,
+									title: Some(item.item.title.clone()),
+									error: e.to_string()
+								});
+							}
+						}
+					}
+				}
+
+				RequestResults::Watcher(v) => {
+					if let Some(error) = v.general_error.as_ref().filter(|_| wants_errors) {
+						updates.errors.push(RequestError { feed_id: None, watch_id: None, title: None, error: error.clone() });
+					}
+
+					for item in &v.items {
+						match &item.results {
+							Ok(res) => {
+								if wants_changes && res.new_item_count != 0 {
+									if let Some(history) = objects::get_last_watch_history(item.item.id, conn)? {
+										updates.watcher_changes.extend(objects::get_watch_history_change(&history, conn)?);
+									}
+								}
+
+								if let Some(reason) = degraded_reason(res).filter(|_| wants_errors) {
+									updates.errors.push(RequestError {
+										feed_id: None,
+										watch_id: Some(item.item.id),
+										title: Some(item.item.title.clone()),
+										error: reason
+									});
+								}
+							}
+
+							Err(e) => if wants_errors {
+								updates.errors.push(RequestError {
+									feed_id: None,
+									watch_id: Some(item.item.id),
+									title: Some(item.item.title.clone()),
+									error: e.to_string()
+								});
+							}
+						}
+					}
+				}
+			}
+		}
+
+		if !feed_ids.is_empty() {
+			let since = resp.start_time.duration_since(UNIX_EPOCH)?.as_secs() as i64;
+
+			updates.items = objects::get_items_added_since(&feed_ids, since, conn)?;
+
+			if topics.iter().any(|t| matches!(t, SocketTopic::Category(_))) {
+				for feed_cat in objects::get_feed_categories(conn)? {
+					updates.feed_categories.entry(feed_cat.feed_id).or_default().push(feed_cat.category_id);
+				}
+			}
+		}
+
+		if let Some(group) = history_group.filter(|_| topics.contains(&SocketTopic::RequestHistory)) {
+			let items = objects::get_request_history_group_items(group.id, conn)?;
+
+			updates.history = Some((group, items));
+		}
+
+		Ok(updates)
+	}
+
+	fn send_to(&self, client: &SocketClient) {
+		let topics = &client.topics;
+		let mut wrapper = WebsocketWrapper::new(&client.recipient);
+
+		let items = self.items.iter()
+			.filter(|item| topics.iter().any(|topic| match topic {
+				SocketTopic::Items => true,
+				SocketTopic::Feed(id) => item.feed_id == *id,
+				SocketTopic::Category(id) => self.feed_categories.get(&item.feed_id).map(|c| c.contains(id)).unwrap_or_default(),
+				_ => false
+			}))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		if !items.is_empty() {
+			wrapper.respond_with(None, Core2FrontNotification::NewItems { items });
+		}
+
+		let changes = self.watcher_changes.iter()
+			.filter(|c| topics.contains(&SocketTopic::Watchers) || topics.contains(&SocketTopic::Watcher(c.watch_id)))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		if !changes.is_empty() {
+			wrapper.respond_with(None, Core2FrontNotification::WatcherChanges { items: changes });
+		}
+
+		if let Some((group, items)) = self.history.as_ref().filter(|_| topics.contains(&SocketTopic::RequestHistory)) {
+			wrapper.respond_with(None, Core2FrontNotification::RequestHistoryUpdate { group: group.clone(), items: items.clone() });
+		}
+
+		if !self.errors.is_empty() && topics.contains(&SocketTopic::Errors) {
+			wrapper.respond_with(None, Core2FrontNotification::RequestErrors { errors: self.errors.clone() });
+		}
+	}
+}
+
+
+fn degraded_reason<I>(res: &RequestItemResults<I>) -> Option<String> {
+	let stats = res.parser_stats.as_ref()?;
+
+	match stats.degraded.as_ref() {
+		Some(reason) => Some(format!("Parser degraded: {}", reason)),
+		None => stats.fallback_reason().map(|v| format!("Parser primary selector failed: {}", v))
+	}
+}
+
+
+/// Updates the clients' subscriptions. Returns what it's now subscribed to.
+fn update_subscriptions(recipient: &Recipient<Line>, update: impl FnOnce(&mut HashSet<SocketTopic>)) -> Vec<SocketTopic> {
+	let mut clients = SOCKET_CLIENTS.lock().unwrap();
+
+	match clients.iter_mut().find(|c| &c.recipient == recipient) {
+		Some(client) => {
+			update(&mut client.topics);
+			client.topics.iter().cloned().collect()
+		}
+
+		None => Vec::new()
+	}
+}
+
+
 pub async fn socket_index(weak_core: web::Data<WeakFeederCore>, r: HttpRequest, stream: web::Payload) -> Result<HttpResponse, ActixError> {
 	ws::start(WebSocket::new(weak_core.as_ref().clone()), &r, stream)
 }
@@ -102,9 +318,11 @@ impl Actor for WebSocket {
 		let mut clients = SOCKET_CLIENTS.lock().unwrap();
 		let weak = ctx.address().recipient();
 
-		if let Some(index) = clients.iter().position(|x| x == &weak) {
+		if let Some(index) = clients.iter().position(|x| x.recipient == weak) {
 			clients.remove(index);
 		}
+
+		crate::metrics::set_websocket_clients(clients.len());
 	}
 }
 
@@ -167,7 +385,14 @@ impl WebSocket {
 		// let init = Core2FrontNotification::Init{};
 		// ctx.text(serde_json::to_string(&init).unwrap());
 
-		SOCKET_CLIENTS.lock().unwrap().push(ctx.address().recipient());
+		let mut clients = SOCKET_CLIENTS.lock().unwrap();
+
+		clients.push(SocketClient {
+			recipient: ctx.address().recipient(),
+			topics: HashSet::new()
+		});
+
+		crate::metrics::set_websocket_clients(clients.len());
 	}
 
 	fn hb(&self, ctx: &mut <Self as Actor>::Context) {
@@ -194,18 +419,34 @@ impl WebSocket {
 		if let Object2CoreNotification::Frontend { message_id, command } = derived {
 			let recipient = ctx.address().recipient();
 
-			let weak_core = self.weak_core.clone();
+			match command {
+				Front2CoreNotification::Subscribe { topics } => {
+					let topics = update_subscriptions(&recipient, |current| current.extend(topics));
 
-			let future = async move {
-				let recipient = recipient;
-				let mut wrap = WebsocketWrapper::new(&recipient);
+					WebsocketWrapper::new(&recipient).respond_with(message_id, Core2FrontNotification::Subscriptions { topics });
+				}
 
-				if let Err(e) = weak_core.handle_response(&mut wrap, message_id, command).await {
-					wrap.respond(message_id, Err(e));
+				Front2CoreNotification::Unsubscribe { topics } => {
+					let topics = update_subscriptions(&recipient, |current| current.retain(|t| !topics.contains(t)));
+
+					WebsocketWrapper::new(&recipient).respond_with(message_id, Core2FrontNotification::Subscriptions { topics });
 				}
-			};
 
-			future.into_actor(self).spawn(ctx);
+				command => {
+					let weak_core = self.weak_core.clone();
+
+					let future = async move {
+						let recipient = recipient;
+						let mut wrap = WebsocketWrapper::new(&recipient);
+
+						if let Err(e) = weak_core.handle_response(&mut wrap, message_id, command).await {
+							wrap.respond(message_id, Err(e));
+						}
+					};
+
+					future.into_actor(self).spawn(ctx);
+				}
+			}
 		}
 
 		Ok(())
@@ -251,7 +492,7 @@ impl<'a> ResponseWrapper for WebsocketWrapper<'a> {
 					Err(err) => {
 						json!({
 							"message_id": message_id,
-							"error": format!("{}", err)
+							"error": err.to_response()
 						})
 					}
 				}
@@ -1,13 +1,17 @@
 use std::{io, thread::{self, JoinHandle}};
 
 use actix_rt::System;
-use serde_json::json;
+use serde_json::{json, Value};
 use actix_files as fs;
-use actix_web::{get, web::{self, Data}, App, HttpServer, HttpResponse};
+use actix_web::{get, post, web::{self, Data}, App, HttpServer, HttpResponse, ResponseError};
 
 use crate::core::WeakFeederCore;
+use crate::feature::{Front2CoreNotification, ResponseWrapper};
+use crate::types::MessageId;
+use crate::{opml, Result};
 use super::{WeakFrontendCore, FrontendCore};
 use super::socket::socket_index;
+use super::{changes, fever, greader};
 
 
 use handlebars::Handlebars;
@@ -52,6 +56,15 @@ impl Web {
 				.app_data(Data::new(core_ref.clone()))
 				// .service(index)
 				.service(scraper_editor)
+				.service(metrics)
+				.service(healthz)
+				.service(readyz)
+				.service(changes::all_watcher_changes)
+				.service(changes::watcher_changes)
+				.service(fever::fever)
+				.service(greader::scope())
+				.service(export_opml)
+				.service(import_opml)
 				// .service(fs::Files::new("/script", "../app/compiled/js"))
 				.service(web::resource("/ws/").route(web::get().to(socket_index)))
 				.service(fs::Files::new("/", "../frontend/dist/frontend").index_file("/dashboard"))
@@ -78,6 +91,79 @@ async fn index() -> HttpResponse {
 }
 
 
+#[get("/metrics")]
+async fn metrics() -> HttpResponse {
+	if crate::metrics::is_enabled() {
+		HttpResponse::Ok()
+			.content_type(prometheus::TEXT_FORMAT)
+			.body(crate::metrics::gather())
+	} else {
+		HttpResponse::NotFound().finish()
+	}
+}
+
+
+#[get("/healthz")]
+async fn healthz() -> HttpResponse {
+	let report = crate::health::report();
+
+	if report.is_alive() {
+		HttpResponse::Ok().json(report)
+	} else {
+		HttpResponse::ServiceUnavailable().json(report)
+	}
+}
+
+#[get("/readyz")]
+async fn readyz() -> HttpResponse {
+	let report = crate::health::report();
+
+	if report.is_ready() {
+		HttpResponse::Ok().json(report)
+	} else {
+		HttpResponse::ServiceUnavailable().json(report)
+	}
+}
+
+
+#[get("/opml")]
+async fn export_opml(weak_core: web::Data<WeakFeederCore>) -> HttpResponse {
+	let core = weak_core.upgrade().unwrap();
+	let inner = core.to_inner();
+
+	match opml::export(inner.connection.connection()) {
+		Ok(body) => HttpResponse::Ok()
+			.content_type("text/x-opml")
+			.insert_header(("Content-Disposition", "attachment; filename=\"watchmen.opml\""))
+			.body(body),
+
+		Err(e) => e.error_response()
+	}
+}
+
+/// Body is the OPML document. Responds with the result of each feed.
+#[post("/opml")]
+async fn import_opml(weak_core: web::Data<WeakFeederCore>, body: String) -> HttpResponse {
+	let mut captured = CapturedResponse(None);
+
+	let resp = weak_core.handle_response(&mut captured, None, Front2CoreNotification::ImportOpml { opml: body }).await
+		.and_then(|_| captured.0.unwrap_or_else(|| Ok(Value::Null)));
+
+	match resp {
+		Ok(value) => HttpResponse::Ok().json(value),
+		Err(e) => e.error_response()
+	}
+}
+
+struct CapturedResponse(Option<Result<Value>>);
+
+impl ResponseWrapper for CapturedResponse {
+	fn respond(&mut self, _: Option<MessageId>, response: Result<Value>) {
+		self.0 = Some(response);
+	}
+}
+
+
 // Scaper
 
 #[get("/scraper/editor")]
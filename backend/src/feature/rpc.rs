@@ -1,12 +1,19 @@
 use serde::{Serialize, Deserialize};
 
 use crate::FilterType;
+use crate::opml::ImportResult;
+use crate::bundle::{ConflictMode, ImportedParser, ParserBundle};
 use crate::request::feeds::custom::{
 	UpdateableCustomItem,
-	CustomItem
+	CustomItem,
+	SearchParser,
+	FoundItem as CustomFoundItem
 };
+use crate::xpath::NodeTrace;
 
-use crate::request::watcher::{FoundItem, MatchParser, UpdateableWatchParser, WatchParserItem};
+use crate::request::watcher::{FoundItem, MatchParser, ParserMatch, UpdateableWatchParser, WatchParserItem};
+use crate::request::structured::StructuredValue;
+use crate::request::page_text::PageText;
 
 use super::models::{
 	CategoryModel,
@@ -29,7 +36,8 @@ use super::models::{
 use super::objects::{
 	NewFilter,
 	FilterGrouping,
-	WatchHistoryBase
+	WatchHistoryBase,
+	WatchHistoryChanges
 };
 
 
@@ -37,11 +45,54 @@ use super::objects::{
 pub struct Empty {}
 
 
+/// What a WebSocket client wants pushed to it after each request cycle.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type", content = "id")]
+pub enum SocketTopic {
+	/// Every new feed item.
+	Items,
+	/// New feed items of feeds in the category.
+	Category(QueryId),
+	/// New feed items of a single feed.
+	Feed(QueryId),
+
+	/// Every watchers' changes.
+	Watchers,
+	/// A single watchers' changes.
+	Watcher(QueryId),
+
+	RequestHistory,
+	Errors
+}
+
+/// An error from a request cycle. Neither id is set for general errors.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestError {
+	pub feed_id: Option<QueryId>,
+	pub watch_id: Option<QueryId>,
+	pub title: Option<String>,
+	pub error: String
+}
+
+
 // Front End -> Core
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "method", content = "params")]
 pub enum Front2CoreNotification {
+	// Web Socket
+
+	/// Only handled by the WebSocket.
+	Subscribe {
+		topics: Vec<SocketTopic>
+	},
+
+	/// Only handled by the WebSocket.
+	Unsubscribe {
+		topics: Vec<SocketTopic>
+	},
+
 	// Dashboard
 
 	/// Most recent first
@@ -60,7 +111,10 @@ pub enum Front2CoreNotification {
 	/// Add something else to listen to.
 	AddListener {
 		url: String,
-		custom_item_id: Option<i32>
+		custom_item_id: Option<i32>,
+		/// Only for sitemaps. Pattern the URLs have to match. ex. `*/products/*`
+		#[serde(default)]
+		url_filter: Option<String>
 	},
 
 	RemoveListener {
@@ -116,6 +170,13 @@ pub enum Front2CoreNotification {
 
 	FeedList(Empty),
 
+	/// OPML 2.0 document.
+	ImportOpml {
+		opml: String
+	},
+
+	ExportOpml(Empty),
+
 	/// Returns updates `since` time.
 	FeedUpdates {
 		since: i64
@@ -142,6 +203,13 @@ pub enum Front2CoreNotification {
 		item: UpdateableCustomItem
 	},
 
+	/// Fails with `parser_in_use` if feeds use it unless `force` is set.
+	RemoveCustomItem {
+		id: QueryId,
+		#[serde(default)]
+		force: bool
+	},
+
 	// Feed Filter
 
 	NewFeedFilter {
@@ -183,7 +251,13 @@ pub enum Front2CoreNotification {
 
 	AddWatcher {
 		url: String,
-		custom_item_id: Option<i32>
+		custom_item_id: Option<i32>,
+		/// Watch the page's schema.org Product data. The parser is then only a fallback.
+		#[serde(default)]
+		structured_data: Option<StructuredValue>,
+		/// Watch the page's text. No parser is used.
+		#[serde(default)]
+		page_text: Option<PageText>
 	},
 
 	RemoveWatcher {
@@ -214,6 +288,18 @@ pub enum Front2CoreNotification {
 		id: QueryId
 	},
 
+	/// Parser bundle of custom items and watch parsers. `None` exports all of them.
+	ExportParsers {
+		custom_item_ids: Option<Vec<QueryId>>,
+		watch_parser_ids: Option<Vec<QueryId>>
+	},
+
+	ImportParsers {
+		bundle: ParserBundle,
+		#[serde(default)]
+		conflict: ConflictMode
+	},
+
 
 	// History
 	WatchHistoryList {
@@ -229,7 +315,17 @@ pub enum Front2CoreNotification {
 	TestWatcher {
 		url: String,
 
-		parser: Option<MatchParser>
+		parser: Option<MatchParser>,
+		#[serde(default)]
+		structured_data: Option<StructuredValue>,
+		#[serde(default)]
+		page_text: Option<PageText>
+	},
+
+	TestCustomItem {
+		url: String,
+
+		parser: SearchParser
 	}
 }
 
@@ -245,6 +341,28 @@ pub enum Core2FrontNotification {
 		feed_items_count: usize
 	},
 
+	Subscriptions {
+		topics: Vec<SocketTopic>
+	},
+
+	// Pushed to subscribed clients after each request cycle.
+	NewItems {
+		items: Vec<FeedItemModel>
+	},
+
+	WatcherChanges {
+		items: Vec<WatchHistoryChanges>
+	},
+
+	RequestHistoryUpdate {
+		group: RequestHistoryGroupModel,
+		items: Vec<RequestHistoryItemModel>
+	},
+
+	RequestErrors {
+		errors: Vec<RequestError>
+	},
+
 	// Dashboard
 	RequestHistoryList {
 		groups: Vec<RequestHistoryGroupModel>,
@@ -289,7 +407,18 @@ pub enum Core2FrontNotification {
 	},
 
 	FeedList {
-		items: Vec<FeedModel>
+		items: Vec<FeedModel>,
+		/// Latest run of each feed whose parser looks broken.
+		#[serde(default)]
+		degraded: Vec<RequestHistoryItemModel>
+	},
+
+	ImportOpml {
+		items: Vec<ImportResult>
+	},
+
+	ExportOpml {
+		opml: String
 	},
 
 	//
@@ -377,9 +506,23 @@ pub enum Core2FrontNotification {
 		affected: usize
 	},
 
+	UpdateCustomItem {
+		item: UpdateableCustomItem,
+		affected: usize
+	},
+
+	/// `feed_ids` are the feeds which used it.
+	RemoveCustomItem {
+		affected: usize,
+		feed_ids: Vec<QueryId>
+	},
+
 
 	WatcherList {
-		items: Vec<(WatchingModel, Option<WatchHistoryBase>)>
+		items: Vec<(WatchingModel, Option<WatchHistoryBase>)>,
+		/// Latest run of each watcher whose parser looks broken.
+		#[serde(default)]
+		degraded: Vec<RequestHistoryItemModel>
 	},
 
 	NewWatcher {
@@ -415,6 +558,14 @@ pub enum Core2FrontNotification {
 		affected: usize
 	},
 
+	ExportParsers {
+		bundle: ParserBundle
+	},
+
+	ImportParsers {
+		items: Vec<ImportedParser>
+	},
+
 	// Watch History
 	WatchHistoryList {
 		items: Vec<WatchHistoryBase>
@@ -423,7 +574,31 @@ pub enum Core2FrontNotification {
 	// Test
 	TestWatcher {
 		success: bool,
-		items: Vec<FoundItem>
+		items: Vec<FoundItem>,
+		/// Every node `items` matched and what each field evaluated to.
+		#[serde(default)]
+		nodes: Vec<NodeTrace>,
+
+		/// Parser found by URL if one wasn't given.
+		#[serde(default)]
+		matched: Option<ParserMatch>,
+		/// Every parser which matched the URL when there's more than one.
+		#[serde(default)]
+		candidates: Vec<ParserMatch>,
+		/// Items came from structured data instead of the parser.
+		#[serde(default)]
+		structured: bool,
+		/// Pages fetched. More than one with pagination.
+		#[serde(default)]
+		pages: usize
+	},
+
+	TestCustomItem {
+		items: Vec<CustomFoundItem>,
+		#[serde(default)]
+		nodes: Vec<NodeTrace>,
+		#[serde(default)]
+		pages: usize
 	}
 }
 
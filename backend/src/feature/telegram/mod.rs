@@ -6,12 +6,12 @@ use tokio::{runtime::Runtime};
 
 use teloxide::prelude::*;
 
-use crate::request::RequestResults;
+use crate::request::{RequestItemResults, RequestResults};
 use crate::request::watcher::FoundItem;
 use crate::state::RequestResponse;
 use crate::{config::Config, feature::database::objects};
 use crate::core::WeakFeederCore;
-use crate::filter;
+use crate::{filter, health, metrics, Result};
 
 pub struct TelegramCore(Arc<Mutex<TelegramState>>, mpsc::Sender<RequestResponse>);
 
@@ -78,10 +78,14 @@ impl TelegramState {
 			let bot = Bot::new(config.telegram.api_key.clone());
 
 			if config.telegram.chat_id.is_none() || config.telegram.chat_id == Some(0) {
+				health::set_telegram_state(health::TelegramState::WaitingForChat);
 				start_listener(bot, config, weak_core);
 			} else {
+				health::set_telegram_state(health::TelegramState::Running);
 				start_output(bot, config, receiver, weak_core);
 			}
+		} else {
+			health::set_telegram_state(health::TelegramState::Disabled);
 		}
 	}
 }
@@ -126,6 +130,8 @@ fn start_listener(bot: Bot, config: Config, weak_core: WeakFeederCore) {
 
 fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse>, weak_core: WeakFeederCore) {
 	thread::spawn(move || {
+		let _guard = health::TelegramThreadGuard;
+
 		let rt = Runtime::new().expect("runtime");
 
 		rt.block_on(async move {
@@ -140,6 +146,25 @@ fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse
 
 				let started_at = resp.start_time.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
 
+				// Only once when a parser stops working. Not on every run after.
+				for (title, url, reason) in newly_degraded(&resp) {
+					let send = bot.send_message(
+						chat_id,
+						format!(
+							"Parser for \"{}\" looks broken: {}\n{}",
+							title,
+							reason,
+							url
+						)
+					).send().await;
+
+					metrics::record_notification("telegram", send.is_ok());
+
+					if let Err(e) = send {
+						log::error!("{:?}", e);
+					}
+				}
+
 				for item in resp.results {
 					match item {
 						RequestResults::Feed(v) => {
@@ -164,6 +189,8 @@ fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse
 										)
 									).send().await;
 
+									metrics::record_notification("telegram", send.is_ok());
+
 									if let Err(e) = send {
 										log::error!("{:?}", e);
 									}
@@ -188,7 +215,10 @@ fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse
 										format!(
 											"{}\n{}\n{}",
 											watcher.title,
-											if watcher_items.len() == 1 {
+											if let Some(diff) = item.diff.as_deref() {
+												// Telegram messages are limited to 4096 characters.
+												diff.chars().take(3000).collect::<String>()
+											} else if watcher_items.len() == 1 {
 												watcher_items.first()
 												.map(|i| i.value.clone())
 												.unwrap_or_default()
@@ -199,6 +229,8 @@ fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse
 										)
 									).send().await;
 
+									metrics::record_notification("telegram", send.is_ok());
+
 									if let Err(e) = send {
 										log::error!("{:?}", e);
 									}
@@ -212,4 +244,35 @@ fn start_output(bot: Bot, config: Config, mut receiver: Receiver<RequestResponse
 
 		log::info!("Stopped running telegram thread.");
 	});
+}
+
+
+/// Title, URL and reason of every feed and watcher whose parser just became degraded.
+fn newly_degraded(resp: &RequestResponse) -> Vec<(String, String, String)> {
+	fn reason<I>(results: &Result<RequestItemResults<I>>) -> Option<String> {
+		results.as_ref().ok()
+			.and_then(|v| v.parser_stats.as_ref())
+			.filter(|v| v.newly_degraded)
+			.and_then(|v| v.degraded.clone())
+	}
+
+	let mut found = Vec::new();
+
+	for result in &resp.results {
+		match result {
+			RequestResults::Feed(v) => for item in &v.items {
+				if let Some(reason) = reason(&item.results) {
+					found.push((item.item.title.clone(), item.item.url.clone(), reason));
+				}
+			}
+
+			RequestResults::Watcher(v) => for item in &v.items {
+				if let Some(reason) = reason(&item.results) {
+					found.push((item.item.title.clone(), item.item.url.clone(), reason));
+				}
+			}
+		}
+	}
+
+	found
 }
\ No newline at end of file
@@ -0,0 +1,280 @@
+use std::path::Path;
+use std::sync::RwLock;
+
+use diesel::{Connection, SqliteConnection};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::config::ConfigHealth;
+use crate::feature::DATABASE_PATH;
+use crate::request::RequestResults;
+use crate::state::RequestResponse;
+
+
+lazy_static! {
+	// Kept outside of the CoreState since that's locked for the whole request cycle.
+	static ref HEALTH: RwLock<HealthState> = RwLock::new(HealthState::new());
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulerState {
+	Starting,
+	Idle,
+	Running,
+	/// Requests are disabled in the config. Loop has exited.
+	Stopped
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelegramState {
+	Disabled,
+	/// Waiting for a message to get the chat id.
+	WaitingForChat,
+	Running,
+	/// Sender thread exited.
+	Stopped
+}
+
+
+struct HealthState {
+	max_cycle_age: i64,
+
+	started_at: i64,
+
+	config_loaded_at: Option<i64>,
+	config_error: Option<String>,
+
+	scheduler: SchedulerState,
+
+	last_cycle_started: Option<i64>,
+	last_cycle_finished: Option<i64>,
+	last_cycle_errors: Vec<String>,
+
+	telegram: TelegramState
+}
+
+impl HealthState {
+	fn new() -> Self {
+		Self {
+			max_cycle_age: ConfigHealth::default().max_cycle_age,
+
+			started_at: chrono::Utc::now().timestamp(),
+
+			config_loaded_at: None,
+			config_error: None,
+
+			scheduler: SchedulerState::Starting,
+
+			last_cycle_started: None,
+			last_cycle_finished: None,
+			last_cycle_errors: Vec::new(),
+
+			telegram: TelegramState::Disabled
+		}
+	}
+}
+
+
+pub fn configure(config: &ConfigHealth) {
+	HEALTH.write().unwrap().max_cycle_age = config.max_cycle_age;
+}
+
+pub fn set_config_status(result: Result<(), String>) {
+	let mut health = HEALTH.write().unwrap();
+
+	match result {
+		Ok(_) => {
+			health.config_loaded_at = Some(chrono::Utc::now().timestamp());
+			health.config_error = None;
+		}
+
+		Err(e) => health.config_error = Some(e)
+	}
+}
+
+pub fn set_telegram_state(state: TelegramState) {
+	HEALTH.write().unwrap().telegram = state;
+}
+
+pub fn request_cycle_started() {
+	let mut health = HEALTH.write().unwrap();
+
+	health.scheduler = SchedulerState::Running;
+	health.last_cycle_started = Some(chrono::Utc::now().timestamp());
+}
+
+pub fn request_cycle_finished(resp: &RequestResponse) {
+	let mut health = HEALTH.write().unwrap();
+
+	health.scheduler = SchedulerState::Idle;
+
+	// ex. "Request Manager is already running!"
+	health.last_cycle_errors = resp.results.iter()
+		.filter_map(|r| match r {
+			RequestResults::Feed(v) => v.general_error.clone(),
+			RequestResults::Watcher(v) => v.general_error.clone()
+		})
+		.collect();
+
+	// A cycle which couldn't run doesn't count. Otherwise a stuck manager would look healthy.
+	if health.last_cycle_errors.is_empty() {
+		health.last_cycle_finished = Some(chrono::Utc::now().timestamp());
+	}
+}
+
+pub fn scheduler_stopped() {
+	HEALTH.write().unwrap().scheduler = SchedulerState::Stopped;
+}
+
+
+/// Sets the Telegram state to Stopped once dropped. Dropped when the thread exits or panics.
+pub struct TelegramThreadGuard;
+
+impl Drop for TelegramThreadGuard {
+	fn drop(&mut self) {
+		set_telegram_state(TelegramState::Stopped);
+	}
+}
+
+
+
+#[derive(Debug, Serialize)]
+pub struct Check<V: Serialize> {
+	pub ok: bool,
+	#[serde(flatten)]
+	pub value: V
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseCheck {
+	pub error: Option<String>
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestCycleCheck {
+	pub last_started: Option<i64>,
+	pub last_finished: Option<i64>,
+	/// Seconds since the last finished cycle. Or since startup if one hasn't finished yet.
+	pub age: i64,
+	pub max_age: i64,
+	pub errors: Vec<String>
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulerCheck {
+	pub state: SchedulerState
+}
+
+#[derive(Debug, Serialize)]
+pub struct TelegramCheck {
+	pub state: TelegramState
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigCheck {
+	pub loaded_at: Option<i64>,
+	pub error: Option<String>
+}
+
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+	pub ok: bool,
+
+	pub database: Check<DatabaseCheck>,
+	pub request_cycle: Check<RequestCycleCheck>,
+	pub scheduler: Check<SchedulerCheck>,
+	pub telegram: Check<TelegramCheck>,
+	pub config: Check<ConfigCheck>
+}
+
+impl HealthReport {
+	/// Liveness. Only cares if the database is reachable and the request loop hasn't stalled.
+	pub fn is_alive(&self) -> bool {
+		self.database.ok && self.request_cycle.ok
+	}
+
+	/// Readiness. Everything has to be fine.
+	pub fn is_ready(&self) -> bool {
+		self.ok
+	}
+}
+
+
+pub fn report() -> HealthReport {
+	let database_error = check_database().err();
+
+	let database = Check {
+		ok: database_error.is_none(),
+		value: DatabaseCheck {
+			error: database_error
+		}
+	};
+
+	let health = HEALTH.read().unwrap();
+
+	let now = chrono::Utc::now().timestamp();
+	let age = now - health.last_cycle_finished.unwrap_or(health.started_at);
+
+	let request_cycle = Check {
+		// Stopped loop won't have any new cycles. That's reported by the scheduler.
+		ok: health.scheduler == SchedulerState::Stopped || age <= health.max_cycle_age,
+		value: RequestCycleCheck {
+			last_started: health.last_cycle_started,
+			last_finished: health.last_cycle_finished,
+			age,
+			max_age: health.max_cycle_age,
+			errors: health.last_cycle_errors.clone()
+		}
+	};
+
+	let scheduler = Check {
+		ok: health.scheduler != SchedulerState::Stopped,
+		value: SchedulerCheck {
+			state: health.scheduler
+		}
+	};
+
+	let telegram = Check {
+		ok: health.telegram != TelegramState::Stopped,
+		value: TelegramCheck {
+			state: health.telegram
+		}
+	};
+
+	let config = Check {
+		ok: health.config_loaded_at.is_some() && health.config_error.is_none(),
+		value: ConfigCheck {
+			loaded_at: health.config_loaded_at,
+			error: health.config_error.clone()
+		}
+	};
+
+	HealthReport {
+		ok: database.ok && request_cycle.ok && scheduler.ok && telegram.ok && config.ok,
+
+		database,
+		request_cycle,
+		scheduler,
+		telegram,
+		config
+	}
+}
+
+
+/// Uses its' own connection since the main one is behind the CoreState lock.
+fn check_database() -> Result<(), String> {
+	// Connecting would create an empty database if it's missing.
+	if !Path::new(DATABASE_PATH).is_file() {
+		return Err(format!("Database file {} is missing.", DATABASE_PATH));
+	}
+
+	let conn = SqliteConnection::establish(DATABASE_PATH).map_err(|e| e.to_string())?;
+
+	conn.execute("SELECT 1").map_err(|e| e.to_string())?;
+
+	Ok(())
+}
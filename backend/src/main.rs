@@ -9,11 +9,17 @@
 pub mod feature;
 
 mod util;
+mod cli;
 pub mod rpc;
 pub mod types;
 pub mod error;
 pub mod filter;
 pub mod config;
+pub mod metrics;
+pub mod health;
+pub mod opml;
+pub mod bundle;
+pub mod backup;
 
 
 pub mod core;
@@ -26,6 +32,17 @@ pub use error::{Result, Error};
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+	let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+	match cli::run(&args).await {
+		Ok(true) => return Ok(()),
+		Ok(false) => (),
+		Err(e) => {
+			eprintln!("{}", e.message());
+			std::process::exit(1);
+		}
+	}
+
 	feature::logging::configure();
 
 	let mut core = core::FeederCore::new();
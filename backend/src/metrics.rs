@@ -0,0 +1,169 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use prometheus::{
+	Encoder, TextEncoder,
+	HistogramVec, IntCounterVec, IntGauge,
+	register_histogram_vec, register_int_counter_vec, register_int_gauge
+};
+
+use crate::config::ConfigMetrics;
+use crate::feature::models::QueryId;
+use crate::feature::DATABASE_PATH;
+use crate::Error;
+
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static ID_LABELS: AtomicBool = AtomicBool::new(false);
+
+
+lazy_static! {
+	static ref FETCHES: IntCounterVec = register_int_counter_vec!(
+		"watchmen_fetches_total",
+		"Amount of times a feed or watcher was requested.",
+		&["source", "id"]
+	).unwrap();
+
+	static ref FETCH_DURATION: HistogramVec = register_histogram_vec!(
+		"watchmen_fetch_duration_seconds",
+		"How long requesting and parsing a feed or watcher took.",
+		&["source", "id"]
+	).unwrap();
+
+	static ref ERRORS: IntCounterVec = register_int_counter_vec!(
+		"watchmen_errors_total",
+		"Errors encountered while requesting a feed or watcher.",
+		&["kind", "source", "id"]
+	).unwrap();
+
+	static ref HTTP_RESPONSES: IntCounterVec = register_int_counter_vec!(
+		"watchmen_http_responses_total",
+		"HTTP responses received, by status class.",
+		&["class"]
+	).unwrap();
+
+	static ref ITEMS_INSERTED: IntCounterVec = register_int_counter_vec!(
+		"watchmen_items_inserted_total",
+		"New feed items inserted into the database.",
+		&["id"]
+	).unwrap();
+
+	static ref WATCHER_CHANGES: IntCounterVec = register_int_counter_vec!(
+		"watchmen_watcher_changes_total",
+		"Changes detected by watchers.",
+		&["id"]
+	).unwrap();
+
+	static ref NOTIFICATIONS: IntCounterVec = register_int_counter_vec!(
+		"watchmen_notifications_total",
+		"Notifications sent, by channel and result.",
+		&["channel", "result"]
+	).unwrap();
+
+	static ref SCHEDULER_LAG: HistogramVec = register_histogram_vec!(
+		"watchmen_scheduler_lag_seconds",
+		"Seconds between a feed or watcher being due and it being requested.",
+		&["source"],
+		vec![1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0]
+	).unwrap();
+
+	static ref WEBSOCKET_CLIENTS: IntGauge = register_int_gauge!(
+		"watchmen_websocket_clients",
+		"Currently connected WebSocket clients."
+	).unwrap();
+
+	static ref DATABASE_SIZE: IntGauge = register_int_gauge!(
+		"watchmen_database_size_bytes",
+		"Size of the SQLite database file."
+	).unwrap();
+}
+
+
+pub fn configure(config: &ConfigMetrics) {
+	ENABLED.store(config.enabled, Ordering::Relaxed);
+	ID_LABELS.store(config.id_labels, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+	ENABLED.load(Ordering::Relaxed)
+}
+
+
+/// Source label for a feeds' `feed_type`.
+pub fn feed_source(feed_type: i32) -> &'static str {
+	match feed_type {
+		0 => "rss",
+		1 => "atom",
+		2 => "custom",
+		3 => "sitemap",
+		_ => "unknown"
+	}
+}
+
+/// Feed and Watcher ids are only added as labels if enabled in the config. An empty label is the same as no label.
+fn id_label(id: QueryId) -> String {
+	if ID_LABELS.load(Ordering::Relaxed) {
+		id.to_string()
+	} else {
+		String::new()
+	}
+}
+
+
+pub fn record_fetch(source: &str, id: QueryId, duration: Duration, error: Option<&Error>) {
+	let id = id_label(id);
+
+	FETCHES.with_label_values(&[source, &id]).inc();
+	FETCH_DURATION.with_label_values(&[source, &id]).observe(duration.as_secs_f64());
+
+	if let Some(error) = error {
+		ERRORS.with_label_values(&[error.name(), source, &id]).inc();
+	}
+}
+
+pub fn record_http_status(status: Option<u16>) {
+	let class = match status {
+		Some(status @ 100..=599) => format!("{}xx", status / 100),
+		_ => String::from("error")
+	};
+
+	HTTP_RESPONSES.with_label_values(&[&class]).inc();
+}
+
+pub fn record_items_inserted(feed_id: QueryId, count: usize) {
+	ITEMS_INSERTED.with_label_values(&[&id_label(feed_id)]).inc_by(count as u64);
+}
+
+pub fn record_watcher_change(watch_id: QueryId) {
+	WATCHER_CHANGES.with_label_values(&[&id_label(watch_id)]).inc();
+}
+
+pub fn record_notification(channel: &str, sent: bool) {
+	NOTIFICATIONS.with_label_values(&[channel, if sent { "sent" } else { "failed" }]).inc();
+}
+
+/// `lag` is in seconds.
+pub fn record_scheduler_lag(source: &str, lag: i64) {
+	SCHEDULER_LAG.with_label_values(&[source]).observe(lag.max(0) as f64);
+}
+
+pub fn set_websocket_clients(count: usize) {
+	WEBSOCKET_CLIENTS.set(count as i64);
+}
+
+
+/// Prometheus text format of every metric.
+pub fn gather() -> String {
+	if let Ok(meta) = std::fs::metadata(DATABASE_PATH) {
+		DATABASE_SIZE.set(meta.len() as i64);
+	}
+
+	let mut buffer = Vec::new();
+
+	if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+		log::error!("Metrics Encoding Error: {}", e);
+	}
+
+	String::from_utf8(buffer).unwrap_or_default()
+}
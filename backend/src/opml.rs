@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use diesel::SqliteConnection;
+use quick_xml::{Reader, Writer};
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use serde::{Serialize, Deserialize};
+use url::Url;
+
+use crate::feature::models::{NewFeedCategoryModel, NewFeedModel, QueryId};
+use crate::feature::objects;
+use crate::request::feeds::UNDETECTED_FEED_TYPE;
+use crate::{Error, Result};
+
+
+/// A feed outline. Categories are the parent outlines and the `category` attribute.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpmlFeed {
+	pub url: String,
+	pub title: Option<String>,
+	pub categories: Vec<String>
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+	Added,
+	/// Feed was already added. Only its categories were updated.
+	Existing,
+	Failed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportResult {
+	pub url: String,
+	pub status: ImportStatus,
+	pub feed_id: Option<QueryId>,
+	pub error: Option<String>
+}
+
+
+pub fn parse(value: &str) -> Result<Vec<OpmlFeed>> {
+	let mut reader = Reader::from_str(value);
+	reader.trim_text(true);
+
+	let mut feeds = Vec::new();
+
+	// Titles of the open non-feed outlines.
+	let mut folders: Vec<Option<String>> = Vec::new();
+
+	let mut buf = Vec::new();
+
+	loop {
+		let event = reader.read_event(&mut buf)?;
+
+		match &event {
+			Event::Start(e) | Event::Empty(e) if e.name() == b"outline" => {
+				let mut attrs = HashMap::new();
+
+				for attr in e.attributes() {
+					let attr = attr?;
+					attrs.insert(String::from_utf8_lossy(attr.key).to_string(), attr.unescape_and_decode_value(&reader)?);
+				}
+
+				let title = attrs.get("title").or_else(|| attrs.get("text")).filter(|v| !v.is_empty()).cloned();
+				let url = attrs.get("xmlUrl").map(|v| v.trim()).filter(|v| !v.is_empty());
+
+				if let Some(url) = url {
+					let mut categories = folders.iter().flatten().cloned().collect::<Vec<_>>();
+
+					// Comma separated. Nested ones are "/" separated (ex. "/News/Tech").
+					for category in attrs.get("category").map(|v| v.split(',')).into_iter().flatten() {
+						if let Some(name) = category.trim().trim_matches('/').rsplit('/').next().filter(|v| !v.is_empty()) {
+							if !categories.iter().any(|v| v == name) {
+								categories.push(name.to_string());
+							}
+						}
+					}
+
+					feeds.push(OpmlFeed {
+						url: url.to_string(),
+						title: title.clone(),
+						categories
+					});
+				}
+
+				// Closed by an Event::End. Feed outlines aren't folders.
+				if matches!(event, Event::Start(_)) {
+					folders.push(if url.is_some() { None } else { title });
+				}
+			}
+
+			Event::End(e) if e.name() == b"outline" => {
+				folders.pop();
+			}
+
+			Event::Eof => break,
+
+			_ => ()
+		}
+
+		buf.clear();
+	}
+
+	Ok(feeds)
+}
+
+
+pub fn export(conn: &SqliteConnection) -> Result<String> {
+	let feeds = objects::get_listeners(conn)?;
+	let categories = objects::get_categories(conn)?;
+	let feed_categories = objects::get_feed_categories(conn)?;
+
+	let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b'\t', 1);
+
+	writer.write_event(Event::Decl(BytesDecl::new(b"1.0", Some(b"UTF-8"), None)))?;
+	writer.write_event(Event::Start(BytesStart::borrowed_name(b"opml").with_attributes(vec![("version", "2.0")])))?;
+
+	writer.write_event(Event::Start(BytesStart::borrowed_name(b"head")))?;
+	write_text_element(&mut writer, b"title", "Watchmen Feeds")?;
+	write_text_element(&mut writer, b"dateCreated", &chrono::Utc::now().to_rfc2822())?;
+	writer.write_event(Event::End(BytesEnd::borrowed(b"head")))?;
+
+	writer.write_event(Event::Start(BytesStart::borrowed_name(b"body")))?;
+
+	for category in &categories {
+		let mut outline = BytesStart::borrowed_name(b"outline");
+		outline.push_attribute(("text", category.name.as_str()));
+		outline.push_attribute(("title", category.name.as_str()));
+
+		writer.write_event(Event::Start(outline))?;
+
+		for feed_cat in feed_categories.iter().filter(|v| v.category_id == category.id) {
+			if let Some(feed) = feeds.iter().find(|v| v.id == feed_cat.feed_id) {
+				writer.write_event(Event::Empty(feed_outline(&feed.title, &feed.url)))?;
+			}
+		}
+
+		writer.write_event(Event::End(BytesEnd::borrowed(b"outline")))?;
+	}
+
+	// Feeds without a category.
+	for feed in feeds.iter().filter(|f| !feed_categories.iter().any(|v| v.feed_id == f.id)) {
+		writer.write_event(Event::Empty(feed_outline(&feed.title, &feed.url)))?;
+	}
+
+	writer.write_event(Event::End(BytesEnd::borrowed(b"body")))?;
+	writer.write_event(Event::End(BytesEnd::borrowed(b"opml")))?;
+
+	Ok(String::from_utf8_lossy(&writer.into_inner().into_inner()).to_string())
+}
+
+
+/// Adds each feed and links it to its categories. Categories are created if they don't exist.
+/// Feeds aren't fetched here. Their type is detected when they're first requested.
+pub fn import(feeds: Vec<OpmlFeed>, conn: &SqliteConnection) -> Result<Vec<ImportResult>> {
+	let mut results = Vec::new();
+
+	for feed in feeds {
+		let result = match import_feed(&feed, conn) {
+			Ok((feed_id, status)) => ImportResult {
+				url: feed.url,
+				status,
+				feed_id: Some(feed_id),
+				error: None
+			},
+
+			Err(e) => {
+				log::error!("OPML Import \"{}\": {}", feed.url, e);
+
+				ImportResult {
+					url: feed.url,
+					status: ImportStatus::Failed,
+					feed_id: None,
+					error: Some(e.message())
+				}
+			}
+		};
+
+		results.push(result);
+	}
+
+	Ok(results)
+}
+
+fn import_feed(feed: &OpmlFeed, conn: &SqliteConnection) -> Result<(QueryId, ImportStatus)> {
+	let (feed_id, status) = match objects::get_listener_by_url(&feed.url, conn)? {
+		Some(existing) => (existing.id, ImportStatus::Existing),
+
+		None => {
+			Url::parse(&feed.url)?;
+
+			let new_feed = NewFeedModel {
+				url: feed.url.clone(),
+
+				enabled: true,
+
+				title: feed.title.clone().unwrap_or_else(|| feed.url.clone()),
+				description: String::new(),
+				generator: String::new(),
+
+				feed_type: UNDETECTED_FEED_TYPE,
+
+				sec_interval: 60 * 5,
+				remove_after: 0,
+
+				global_show: true,
+				ignore_if_not_new: true,
+
+				date_added: chrono::Utc::now().timestamp(),
+				// Requested on the next cycle.
+				last_called: 0,
+
+				url_filter: None
+			};
+
+			objects::create_listener(&new_feed, conn).map_err(|e| Error::from_url_insert(e, &feed.url))?;
+
+			let created = objects::get_listener_by_url(&feed.url, conn)?.ok_or(diesel::result::Error::NotFound)?;
+
+			(created.id, ImportStatus::Added)
+		}
+	};
+
+	let feed_categories = objects::get_feed_categories(conn)?;
+
+	for name in &feed.categories {
+		let category_id = objects::get_or_create_category(name, conn)?.id;
+
+		if !feed_categories.iter().any(|v| v.feed_id == feed_id && v.category_id == category_id) {
+			objects::create_category_feed(&NewFeedCategoryModel { feed_id, category_id }, conn)?;
+		}
+	}
+
+	Ok((feed_id, status))
+}
+
+
+fn feed_outline<'a>(title: &'a str, url: &'a str) -> BytesStart<'a> {
+	BytesStart::borrowed_name(b"outline").with_attributes(vec![
+		("type", "rss"),
+		("text", title),
+		("title", title),
+		("xmlUrl", url),
+		("htmlUrl", url)
+	])
+}
+
+fn write_text_element(writer: &mut Writer<Cursor<Vec<u8>>>, name: &[u8], value: &str) -> Result<()> {
+	writer.write_event(Event::Start(BytesStart::borrowed_name(name)))?;
+	writer.write_event(Event::Text(BytesText::from_plain_str(value)))?;
+	writer.write_event(Event::End(BytesEnd::borrowed(name)))?;
+
+	Ok(())
+}
@@ -0,0 +1,383 @@
+// Dates from custom feeds. Pages use anything from RFC 2822 to "3 hours ago".
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+
+/// Abbreviations accepted as a timezone. Offsets are in seconds.
+const ZONES: [(&str, i32); 24] = [
+	("UTC", 0), ("GMT", 0), ("Z", 0), ("WET", 0),
+	("WEST", 3600), ("BST", 3600), ("CET", 3600), ("CEST", 2 * 3600),
+	("EET", 2 * 3600), ("EEST", 3 * 3600), ("MSK", 3 * 3600), ("IST", 5 * 3600 + 1800),
+	("JST", 9 * 3600), ("KST", 9 * 3600), ("AEST", 10 * 3600), ("AEDT", 11 * 3600),
+	("EST", -5 * 3600), ("EDT", -4 * 3600), ("CST", -6 * 3600), ("CDT", -5 * 3600),
+	("MST", -7 * 3600), ("MDT", -6 * 3600), ("PST", -8 * 3600), ("PDT", -7 * 3600)
+];
+
+/// Month names and abbreviations. English, German, French, Spanish, Italian, Portuguese and Dutch.
+const MONTHS: [&[&str]; 12] = [
+	&["jan", "january", "januar", "jänner", "jän", "janvier", "janv", "enero", "ene", "gennaio", "gen", "janeiro", "januari"],
+	&["feb", "february", "februar", "février", "fevrier", "févr", "fevr", "fév", "febrero", "febbraio", "fevereiro", "fev", "februari"],
+	&["mar", "march", "märz", "maerz", "mär", "mrz", "mars", "marzo", "março", "marco", "maart", "mrt"],
+	&["apr", "april", "avril", "avr", "abril", "abr", "aprile"],
+	&["may", "mai", "mayo", "maggio", "mag", "maio", "mei"],
+	&["jun", "june", "juni", "juin", "junio", "giugno", "giu", "junho"],
+	&["jul", "july", "juli", "juillet", "juil", "julio", "luglio", "lug", "julho"],
+	&["aug", "august", "août", "aout", "agosto", "ago", "augustus"],
+	&["sep", "sept", "september", "septembre", "septiembre", "settembre", "set", "setembro"],
+	&["oct", "october", "oktober", "okt", "octobre", "octubre", "ottobre", "ott", "outubro", "out"],
+	&["nov", "november", "novembre", "noviembre", "novembro"],
+	&["dec", "december", "dezember", "dez", "décembre", "decembre", "déc", "diciembre", "dic", "dicembre", "dezembro"]
+];
+
+
+lazy_static! {
+	static ref TIME: Regex = Regex::new(r"(?i)(\d{1,2})[:h](\d{2})(?::(\d{2}))?(?:\.\d+)?(?:\s*([ap])\.?m\b\.?)?").unwrap();
+	static ref TIME_OFFSET: Regex = Regex::new(r"(\d:\d{2}(?::\d{2})?(?:\.\d+)?)\s*([+-]\d{2}:?\d{2})$").unwrap();
+	static ref RELATIVE: Regex = Regex::new(r"^(\d+|an?|one)\s*([a-z]+)\.?\s+ago$").unwrap();
+
+	static ref ISO_DATE: Regex = Regex::new(r"^(\d{4})[-/.](\d{1,2})[-/.](\d{1,2})(?:[Tt\s,]+(.*))?$").unwrap();
+	static ref DOTTED_DATE: Regex = Regex::new(r"^(\d{1,2})\.(\d{1,2})\.(\d{2}|\d{4})(?:[\s,]+(.*))?$").unwrap();
+	static ref SLASHED_DATE: Regex = Regex::new(r"^(\d{1,2})/(\d{1,2})/(\d{4})(?:[\s,]+(.*))?$").unwrap();
+
+	static ref WORD: Regex = Regex::new(r"[^\W\d_]+").unwrap();
+	static ref NUMBER: Regex = Regex::new(r"\d+").unwrap();
+}
+
+
+/// Parses a date found on a page. `timezone` is used if the value doesn't have one, otherwise UTC.
+///
+/// Accepts RFC 3339 / 2822, ISO variants, `01.03.2024`, `03/01/2024` (day first if it's over 12),
+/// Unix timestamps in seconds or milliseconds, "3 hours ago", "yesterday 10:00" and month names
+/// in a few languages ("1. März 2024", "Mar 1st, 2024 at 2:00 PM").
+pub fn parse_date(value: &str, timezone: Option<FixedOffset>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+	let value = value.trim();
+
+	if value.is_empty() {
+		return None;
+	}
+
+	if let Ok(date) = DateTime::parse_from_rfc3339(value).or_else(|_| DateTime::parse_from_rfc2822(value)) {
+		return Some(date.with_timezone(&Utc));
+	}
+
+	if let Some(date) = parse_timestamp(value) {
+		return Some(date);
+	}
+
+	let (value, offset) = split_offset(value);
+
+	let offset = offset.or(timezone).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+	let local_now = now.with_timezone(&offset).naive_local();
+
+	let lowercase = value.to_lowercase();
+
+	// "ago" is also a month abbreviation so "N units ago" isn't tried as anything else.
+	let naive = if RELATIVE.is_match(&lowercase) {
+		parse_relative(&lowercase, local_now)?
+	} else {
+		parse_relative(&lowercase, local_now)
+			.or_else(|| parse_numeric(&lowercase))
+			.or_else(|| parse_named(&lowercase, local_now))?
+	};
+
+	offset.from_local_datetime(&naive).single().map(|v| v.with_timezone(&Utc))
+}
+
+/// `+02:00`, `-0500`, `UTC+2` or an abbreviation like `CET`.
+pub fn parse_timezone(value: &str) -> Option<FixedOffset> {
+	let value = value.trim().to_uppercase();
+
+	let (name, offset) = match value.find(['+', '-']) {
+		Some(pos) => (&value[..pos], Some(&value[pos..])),
+		None => (value.as_str(), None)
+	};
+
+	let base = match name {
+		"" if offset.is_none() => return None,
+		"" => 0,
+		name => ZONES.iter().find(|(v, _)| *v == name)?.1
+	};
+
+	let extra = match offset {
+		Some(v) => parse_offset(v)?,
+		None => 0
+	};
+
+	FixedOffset::east_opt(base + extra)
+}
+
+
+/// Seconds from a signed offset. `+2`, `+05:30` or `-0500`.
+fn parse_offset(value: &str) -> Option<i32> {
+	let sign = if value.starts_with('-') { -1 } else { 1 };
+	let value = &value[1..];
+
+	let (hours, minutes) = match value.split_once(':') {
+		Some((h, m)) => (h, m),
+		None if value.len() == 4 => value.split_at(2),
+		None => (value, "0")
+	};
+
+	let hours: i32 = hours.parse().ok()?;
+	let minutes: i32 = minutes.parse().ok()?;
+
+	if hours > 14 || minutes >= 60 {
+		return None;
+	}
+
+	Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Seconds or milliseconds since the epoch.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+	if !value.chars().all(|c| c.is_ascii_digit()) {
+		return None;
+	}
+
+	let number: i64 = value.parse().ok()?;
+
+	match value.len() {
+		9 | 10 => DateTime::from_timestamp(number, 0),
+		12 | 13 => DateTime::from_timestamp_millis(number),
+		_ => None
+	}
+}
+
+/// Removes an offset after the time ("10:00+02:00", "10:00Z") or a trailing zone ("10:00 CET").
+fn split_offset(value: &str) -> (&str, Option<FixedOffset>) {
+	if let Some(caps) = TIME_OFFSET.captures(value) {
+		let end = caps.get(1).unwrap().end();
+
+		return (&value[..end], parse_timezone(&caps[2]));
+	}
+
+	if let Some(rest) = value.strip_suffix(['Z', 'z']).filter(|v| v.ends_with(|c: char| c.is_ascii_digit())) {
+		return (rest, FixedOffset::east_opt(0));
+	}
+
+	if let Some((rest, zone)) = value.rsplit_once(char::is_whitespace) {
+		let zone = zone.trim_matches(|c| c == '(' || c == ')');
+
+		if zone.starts_with(|c: char| c.is_ascii_alphabetic()) {
+			if let Some(offset) = parse_timezone(zone) {
+				return (rest.trim_end(), Some(offset));
+			}
+		}
+	}
+
+	(value, None)
+}
+
+/// "now", "today", "yesterday 10:00" or "3 hours ago".
+fn parse_relative(value: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+	let day_ago = |days: i64, rest: &str| {
+		let time = TIME.captures(rest).and_then(|v| time_from(&v)).unwrap_or(NaiveTime::MIN);
+
+		Some((now.date() - Duration::days(days)).and_time(time))
+	};
+
+	match value {
+		"now" | "just now" | "right now" => return Some(now),
+		v if v.starts_with("today") => return day_ago(0, &v[5..]),
+		v if v.starts_with("yesterday") => return day_ago(1, &v[9..]),
+		_ => ()
+	}
+
+	let caps = RELATIVE.captures(value)?;
+
+	let amount: i64 = match &caps[1] {
+		"a" | "an" | "one" => 1,
+		v => v.parse().ok()?
+	};
+
+	let seconds = match &caps[2] {
+		"s" | "sec" | "secs" | "second" | "seconds" => 1,
+		"m" | "min" | "mins" | "minute" | "minutes" => 60,
+		"h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+		"d" | "day" | "days" => 86400,
+		"w" | "wk" | "wks" | "week" | "weeks" => 7 * 86400,
+		"mo" | "mos" | "month" | "months" => 30 * 86400,
+		"y" | "yr" | "yrs" | "year" | "years" => 365 * 86400,
+		_ => return None
+	};
+
+	// Scraped values can be anything. ex. "99999999 years ago"
+	now.checked_sub_signed(Duration::try_seconds(amount.checked_mul(seconds)?)?)
+}
+
+/// "2024-03-01 10:00", "01.03.2024" and "03/01/2024".
+fn parse_numeric(value: &str) -> Option<NaiveDateTime> {
+	let (year, month, day, rest) = if let Some(caps) = ISO_DATE.captures(value) {
+		(number(&caps, 1)?, number(&caps, 2)?, number(&caps, 3)?, caps.get(4))
+	} else if let Some(caps) = DOTTED_DATE.captures(value) {
+		(number(&caps, 3)?, number(&caps, 2)?, number(&caps, 1)?, caps.get(4))
+	} else if let Some(caps) = SLASHED_DATE.captures(value) {
+		let (first, second) = (number(&caps, 1)?, number(&caps, 2)?);
+
+		// Month first unless it can't be one.
+		let (month, day) = if first > 12 { (second, first) } else { (first, second) };
+
+		(number(&caps, 3)?, month, day, caps.get(4))
+	} else {
+		return None;
+	};
+
+	let year = if year < 100 { year + 2000 } else { year };
+
+	let time = match rest.map(|v| v.as_str()) {
+		Some(rest) => TIME.captures(rest).and_then(|v| time_from(&v)).unwrap_or(NaiveTime::MIN),
+		None => NaiveTime::MIN
+	};
+
+	Some(NaiveDate::from_ymd_opt(year as i32, month, day)?.and_time(time))
+}
+
+/// Dates with a month name. The year is the current one if missing, or last year if that's in the future.
+fn parse_named(value: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+	// The last one since weekdays (Spanish "mar.") can look like months.
+	let month = WORD.find_iter(value)
+		.filter_map(|word| MONTHS.iter().position(|names| names.contains(&word.as_str())))
+		.last()? as u32 + 1;
+
+	let (time, rest) = match TIME.captures(value) {
+		Some(caps) => {
+			let found = caps.get(0).unwrap();
+			(time_from(&caps)?, format!("{} {}", &value[..found.start()], &value[found.end()..]))
+		}
+
+		None => (NaiveTime::MIN, value.to_string())
+	};
+
+	let numbers = NUMBER.find_iter(&rest).map(|v| v.as_str()).collect::<Vec<_>>();
+
+	// Without a day or year it's most likely text with a month word in it. ex. "Sold out" (Portuguese "out").
+	if numbers.is_empty() {
+		return None;
+	}
+
+	let year = numbers.iter().find(|v| v.len() == 4).and_then(|v| v.parse::<i32>().ok());
+	let day = numbers.iter().find(|v| v.len() <= 2).and_then(|v| v.parse::<u32>().ok()).unwrap_or(1);
+
+	let date = NaiveDate::from_ymd_opt(year.unwrap_or_else(|| now.year()), month, day)?.and_time(time);
+
+	if year.is_none() && date > now + Duration::days(1) {
+		return Some(NaiveDate::from_ymd_opt(now.year() - 1, month, day)?.and_time(time));
+	}
+
+	Some(date)
+}
+
+fn time_from(caps: &Captures<'_>) -> Option<NaiveTime> {
+	let mut hour = number(caps, 1)?;
+	let minute = number(caps, 2)?;
+	let second = number(caps, 3).unwrap_or(0);
+
+	match caps.get(4).map(|v| v.as_str().to_lowercase()).as_deref() {
+		Some("a") if hour == 12 => hour = 0,
+		Some("p") if hour < 12 => hour += 12,
+		_ => ()
+	}
+
+	NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+fn number(caps: &Captures<'_>, index: usize) -> Option<u32> {
+	caps.get(index)?.as_str().parse().ok()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn now() -> DateTime<Utc> {
+		Utc.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap()
+	}
+
+	fn parse(value: &str) -> Option<String> {
+		parse_date(value, None, now()).map(|v| v.to_rfc3339())
+	}
+
+	#[test]
+	fn rfc_formats() {
+		assert_eq!(parse("2024-03-01T10:00:00+02:00").as_deref(), Some("2024-03-01T08:00:00+00:00"));
+		assert_eq!(parse("Fri, 01 Mar 2024 10:00:00 GMT").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+	}
+
+	#[test]
+	fn timestamps() {
+		assert_eq!(parse("1709287200").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+		assert_eq!(parse("1709287200000").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+	}
+
+	#[test]
+	fn relative() {
+		assert_eq!(parse("now").as_deref(), Some("2024-03-10T12:00:00+00:00"));
+		assert_eq!(parse("3 hours ago").as_deref(), Some("2024-03-10T09:00:00+00:00"));
+		assert_eq!(parse("an hour ago").as_deref(), Some("2024-03-10T11:00:00+00:00"));
+		assert_eq!(parse("2 days ago").as_deref(), Some("2024-03-08T12:00:00+00:00"));
+		assert_eq!(parse("Yesterday 10:00").as_deref(), Some("2024-03-09T10:00:00+00:00"));
+	}
+
+	#[test]
+	fn numeric() {
+		assert_eq!(parse("2024-03-01 10:00").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+		assert_eq!(parse("2024-03-01T10:00:00").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+		assert_eq!(parse("2024-03-01T10:00").as_deref(), Some("2024-03-01T10:00:00+00:00"));
+		assert_eq!(parse("01.03.2024").as_deref(), Some("2024-03-01T00:00:00+00:00"));
+		assert_eq!(parse("25/03/2024").as_deref(), Some("2024-03-25T00:00:00+00:00"));
+		assert_eq!(parse("2024-03-01 10:00 CET").as_deref(), Some("2024-03-01T09:00:00+00:00"));
+	}
+
+	#[test]
+	fn month_names() {
+		assert_eq!(parse("1. März 2024").as_deref(), Some("2024-03-01T00:00:00+00:00"));
+		assert_eq!(parse("15 janvier 2024 14:30").as_deref(), Some("2024-01-15T14:30:00+00:00"));
+		assert_eq!(parse("3 de febrero de 2024").as_deref(), Some("2024-02-03T00:00:00+00:00"));
+		assert_eq!(parse("Mar 1st, 2024 at 2:00 PM").as_deref(), Some("2024-03-01T14:00:00+00:00"));
+		// No year and after today so it's last year.
+		assert_eq!(parse("Dec 24").as_deref(), Some("2023-12-24T00:00:00+00:00"));
+	}
+
+	#[test]
+	fn month_words_without_numbers() {
+		assert_eq!(parse("Sold out"), None);
+		assert_eq!(parse("sign up to find out"), None);
+		assert_eq!(parse("set"), None);
+		assert_eq!(parse("March"), None);
+	}
+
+	#[test]
+	fn time_format_output() {
+		use super::super::parse::Parse;
+
+		let value = Parse::TimeFormat("%d.%m.%Y %H:%M".into(), None).apply("01.03.2024 10:00".into(), None).unwrap();
+
+		assert_eq!(parse(&value).as_deref(), Some("2024-03-01T10:00:00+00:00"));
+	}
+
+	#[test]
+	fn timezone_default() {
+		let cet = parse_timezone("CET");
+
+		assert_eq!(cet, FixedOffset::east_opt(3600));
+		assert_eq!(parse_date("2024-03-01 10:00", cet, now()).map(|v| v.to_rfc3339()).as_deref(), Some("2024-03-01T09:00:00+00:00"));
+		assert_eq!(parse_timezone("UTC+5:30"), FixedOffset::east_opt(5 * 3600 + 1800));
+		assert_eq!(parse_timezone("XYZ"), None);
+	}
+
+	#[test]
+	fn out_of_range() {
+		assert_eq!(parse(""), None);
+		assert_eq!(parse("99999999 years ago"), None);
+		assert_eq!(parse("9223372036854775807 seconds ago"), None);
+		assert_eq!(parse("99999999999999999999999 weeks ago"), None);
+		assert_eq!(parse("5 fortnights ago"), None);
+		assert_eq!(parse("2024-13-45"), None);
+		assert_eq!(parse("31.02.2024"), None);
+		assert_eq!(parse("25:61 today"), None);
+		assert_eq!(parse("not a date"), None);
+	}
+}
@@ -1,18 +1,30 @@
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use regex::Regex;
 use reqwest::Client;
 use serde::{Serialize, Deserialize};
 use url::Url;
 
-use regex::RegexBuilder;
 use xpather::value::Node;
 use xpather::Document;
-use chrono::{DateTime, FixedOffset};
 
-use crate::feature::models::QueryId;
-use crate::{Result, Error, xpath};
+use crate::feature::models::{EditCustomItemModel, QueryId};
+use crate::{Result, Error, http};
+use crate::xpath::NodeTrace;
+use crate::selector::{self, ItemNode, Page, SelectorLanguage};
+use crate::links::LinkResolver;
 use super::NewFeedModel;
+use crate::request::ParserStats;
+use crate::request::pagination::{Pages, Pagination};
 
 use crate::feature::objects::{get_custom_item_from_url, get_custom_item_by_id};
 
+pub mod date;
+mod parse;
+
+pub use parse::Parse;
+
 
 pub type CustomResult = Result<Vec<FoundItem>>;
 
@@ -22,7 +34,21 @@ pub struct UpdateableCustomItem {
 	pub description: Option<String>,
 	pub match_url: Option<String>,
 
-	pub search_opts: Option<SearchParser>
+	pub search_opts: Option<SearchParser>,
+	pub priority: Option<i32>
+}
+
+
+impl From<UpdateableCustomItem> for EditCustomItemModel {
+	fn from(val: UpdateableCustomItem) -> Self {
+		EditCustomItemModel {
+			title: val.title,
+			description: val.description,
+			match_url: val.match_url,
+			search_opts: val.search_opts.as_ref().map(serde_json::to_string).and_then(|v| v.ok()),
+			priority: val.priority
+		}
+	}
 }
 
 
@@ -34,12 +60,18 @@ pub struct CustomItem {
 	pub description: String,
 	pub match_url: String,
 
-	pub search_opts: SearchParser
+	pub search_opts: SearchParser,
+
+	/// Higher is picked first when more than one `match_url` matches.
+	#[serde(default)]
+	pub priority: i32
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchParser {
 	pub items: String,
+	#[serde(default)]
+	pub items_language: SelectorLanguage,
 
 	pub title: ParseOpts,
 	pub link: ParseOpts,
@@ -47,83 +79,141 @@ pub struct SearchParser {
 	pub date: ParseOpts,
 
 	pub author: Option<ParseOpts>,
-	pub content: Option<ParseOpts>
+	pub content: Option<ParseOpts>,
+
+	/// Removes tracking query parameters (utm_*, fbclid, ..) from resolved links.
+	#[serde(default)]
+	pub strip_tracking: bool,
+
+	/// Used for dates without an offset. ex. "+02:00" or "CET". UTC if not set.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub timezone: Option<String>,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub pagination: Option<Pagination>
+}
+
+impl SearchParser {
+	/// Checks every selector and parse option so a broken parser isn't saved.
+	pub fn validate(&self) -> Result<()> {
+		selector::validate("items", &self.items, self.items_language, self.items_language)?;
+
+		if let Some(timezone) = self.timezone.as_deref().filter(|v| date::parse_timezone(v).is_none()) {
+			return Err(Error::InvalidField("timezone".into(), format!("Unknown timezone \"{}\". Use an offset like +02:00 or an abbreviation like CET.", timezone)));
+		}
+
+		if let Some(pagination) = self.pagination.as_ref() {
+			pagination.validate(self.items_language)?;
+		}
+
+		let fields = [
+			("title", Some(&self.title)),
+			("link", Some(&self.link)),
+			("guid", Some(&self.guid)),
+			("date", Some(&self.date)),
+			("author", self.author.as_ref()),
+			("content", self.content.as_ref())
+		];
+
+		for (name, opts) in fields {
+			if let Some(opts) = opts {
+				for value in opts.selectors() {
+					selector::validate(name, value, opts.language, self.items_language)?;
+				}
+
+				opts.validate_steps(name)?;
+			}
+		}
+
+		Ok(())
+	}
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FoundItem {
 	pub title: String,
 	pub link: String,
 	pub guid: String,
 	pub date: String,
+	/// `date` as a Unix timestamp. None if it couldn't be parsed.
+	#[serde(default)]
+	pub timestamp: Option<i64>,
 
 	pub author: Option<String>,
 	pub content: Option<String>
 }
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum Parse {
-	None,
-	// Expression
-	Regex(String),
-	// Format, Region (PST, EST)
-	TimeFormat(String, Option<String>)
-}
-
-impl Default for Parse {
-	fn default() -> Self {
-		Parse::None
-	}
-}
-
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ParseOpts {
+	/// XPath or CSS selector depending on `language`.
 	pub xpath: String,
-	pub parse_type: Parse
+	/// Tried in order when `xpath` doesn't give a value. Same language and steps.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub fallbacks: Vec<String>,
+	/// Steps run in order on the value.
+	#[serde(default, deserialize_with = "parse::deserialize_steps")]
+	pub parse_type: Vec<Parse>,
+	#[serde(default)]
+	pub language: SelectorLanguage,
+
+	/// `Regex` steps compiled on first use, by step. The same steps run for every node.
+	#[serde(skip)]
+	regexes: OnceLock<Vec<Option<Regex>>>
 }
 
 impl ParseOpts {
-	pub fn evaluate<'a>(&self, doc: &'a Document, node: &'a Node) -> xpather::Result<xpather::factory::ProduceIter<'a>> {
-		doc.evaluate_from(&self.xpath, node)
+	pub fn evaluate<'a>(&self, selector: &str, doc: &'a Document, node: &'a Node) -> xpather::Result<xpather::factory::ProduceIter<'a>> {
+		doc.evaluate_from(selector, node)
 	}
 
-	pub fn parse(&self, value: &str) -> Result<String> {
-		Ok(
-			match &self.parse_type {
-				Parse::Regex(expr) => {
-					RegexBuilder::new(expr)
-						.case_insensitive(true)
-						.build()?
-						.captures(value)
-						.expect("capture")
-						.get(1)
-						.expect("get")
-						.as_str()
-						.to_string()
-				}
+	/// `xpath` then every fallback.
+	pub fn selectors(&self) -> impl Iterator<Item = &str> {
+		std::iter::once(self.xpath.as_str()).chain(self.fallbacks.iter().map(|v| v.as_str()))
+	}
+
+	/// Checks every step. `Attr` and `Text` only work as the first step.
+	pub fn validate_steps(&self, field: &str) -> Result<()> {
+		for (index, step) in self.parse_type.iter().enumerate() {
+			step.validate(field)?;
 
-				Parse::TimeFormat(parse, offset) => {
-					let parse = if parse.contains("%Z") || offset.is_none() {
-						parse.to_string()
-					} else {
-						format!("{} %Z", parse)
-					};
+			if index != 0 && matches!(step, Parse::Attr(_) | Parse::Text) {
+				return Err(Error::InvalidField(field.into(), format!("{} has to be the first step.", step.name())));
+			}
+		}
 
-					let value = if let Some(offset) = offset {
-						format!("{} {}", value, offset)
-					} else {
-						value.to_string()
-					};
+		Ok(())
+	}
 
-					let date: DateTime<FixedOffset> = DateTime::parse_from_str(&value, &parse)?;
+	/// Whether every node the selector found is used instead of the first.
+	pub fn joins(&self) -> bool {
+		self.parse_type.iter().any(|v| matches!(v, Parse::Join(_)))
+	}
 
-					date.to_rfc2822()
-				}
+	/// `Attr` or `Text` if the value isn't read the selector's default way.
+	pub fn extraction(&self) -> Option<&Parse> {
+		self.parse_type.first().filter(|v| matches!(v, Parse::Attr(_) | Parse::Text))
+	}
 
-				_ => value.to_string()
+	/// Runs the steps on the selected values. Returns the first value left.
+	pub fn apply(&self, mut values: Vec<String>) -> Result<Option<String>> {
+		let regexes = match self.regexes.get() {
+			Some(v) => v,
+			None => {
+				let compiled = self.parse_type.iter().map(Parse::compile).collect::<Result<Vec<_>>>()?;
+				self.regexes.get_or_init(|| compiled)
 			}
-		)
+		};
+
+		for (step, regex) in self.parse_type.iter().zip(regexes) {
+			values = match step {
+				Parse::Join(_) if values.is_empty() => values,
+				Parse::Join(separator) => vec![values.join(separator)],
+				step => values.into_iter().map(|v| step.apply(v, regex.as_ref())).collect::<Result<_>>()?
+			};
+		}
+
+		Ok(values.into_iter().next())
 	}
 }
 
@@ -131,7 +221,10 @@ impl From<&str> for ParseOpts {
 	fn from(value: &str) -> Self {
 		Self {
 			xpath: value.to_string(),
-			parse_type: Parse::None
+			fallbacks: Vec::new(),
+			parse_type: Vec::new(),
+			language: SelectorLanguage::Xpath,
+			regexes: OnceLock::new()
 		}
 	}
 }
@@ -140,7 +233,10 @@ impl From<String> for ParseOpts {
 	fn from(value: String) -> Self {
 		Self {
 			xpath: value,
-			parse_type: Parse::None
+			fallbacks: Vec::new(),
+			parse_type: Vec::new(),
+			language: SelectorLanguage::Xpath,
+			regexes: OnceLock::new()
 		}
 	}
 }
@@ -160,7 +256,7 @@ pub fn new_from_url(url: String, custom_item_id: Option<QueryId>, conn: &diesel:
 	let item = if let Some(id) = custom_item_id {
 		get_custom_item_by_id(id, conn)?
 	} else {
-		get_custom_item_from_url(Url::parse(&url).unwrap(), conn)?
+		get_custom_item_from_url(Url::parse(&url)?, conn).map_err(|e| Error::from_parser_lookup(e, &url))?
 	};
 
 	Ok(NewFeedModel {
@@ -182,6 +278,8 @@ pub fn new_from_url(url: String, custom_item_id: Option<QueryId>, conn: &diesel:
 
 		date_added: chrono::Utc::now().naive_utc().timestamp(),
 		last_called: chrono::Utc::now().naive_utc().timestamp(),
+
+		url_filter: None
 	})
 }
 
@@ -205,64 +303,98 @@ pub fn new_from_feed(url: String, feed: CustomItem) -> NewFeedModel {
 
 		date_added: chrono::Utc::now().naive_utc().timestamp(),
 		last_called: chrono::Utc::now().naive_utc().timestamp(),
+
+		url_filter: None
 	}
 }
 
 
 pub async fn get_from_url(url: &str, req_client: &Client, conn: &diesel::SqliteConnection) -> CustomResult {
-	let found = get_custom_item_from_url(Url::parse(url).unwrap(), conn)?;
+	Ok(get_from_url_with_stats(url, req_client, conn).await?.0)
+}
 
-	// turn found into SearchParser
+pub async fn get_from_url_with_stats(url: &str, req_client: &Client, conn: &diesel::SqliteConnection) -> Result<(Vec<FoundItem>, ParserStats)> {
+	let found = get_custom_item_from_url(Url::parse(url)?, conn).map_err(|e| Error::from_parser_lookup(e, url))?;
 
-	get_from_url_parser(url, &found.search_opts, req_client).await
+	let (items, nodes, pages) = test_url_parser(url, &found.search_opts, req_client).await?;
+
+	for node in nodes.iter().filter(|v| v.error.is_some()) {
+		log::error!("EVALUATION ERROR: {:?}", node);
+	}
+
+	Ok((items, ParserStats::new(&nodes, pages)))
 }
 
-pub async fn get_from_url_parser(url: &str, parser: &SearchParser, req_client: &Client) -> CustomResult {
-	let resp = req_client.get(url).send().await?.text().await?;
-
-	let mut reader = std::io::Cursor::new(resp);
-
-	let doc = xpather::parse_document(&mut reader)?;
-
-	Ok(
-		doc.evaluate(&parser.items)?
-		.collect_nodes()?
-		.into_iter()
-		.map::<Result<FoundItem>, _>(|node| {
-			let title = xpath::get_optional_string(Some(&parser.title), &doc, &node)?;
-			let author = xpath::get_optional_string(parser.author.as_ref(), &doc, &node)?;
-
-			let content = parser.content.as_ref()
-				.map(|i| i.evaluate(&doc, &node))
-				.transpose()?
-				.and_then(|mut v| v.next())
-				.transpose()?
-				.map(|v| Result::Ok(v.as_node()?.as_simple_html()))
-				.transpose()?.flatten()
-				.map(|v| Result::Ok(parser.content.as_ref().unwrap().parse(&v)?))
-				.transpose()?;
-
-			let date = xpath::get_optional_string(Some(&parser.date), &doc, &node)?;
-			let guid = xpath::get_optional_string(Some(&parser.guid), &doc, &node)?;
-			let link = xpath::get_optional_string(Some(&parser.link), &doc, &node)?;
-
-			Ok(FoundItem {
-				title: title.ok_or_else(|| Error::Other("Missing Required Title.".into()))?,
-				link: link.ok_or_else(|| Error::Other("Missing Required Link.".into()))?,
-				guid: guid.ok_or_else(|| Error::Other("Missing Required GUID.".into()))?,
-				date: date.unwrap_or_default(),
-
-				author,
-				content
-			})
-		})
-		.filter_map(|i| {
-			if i.is_err() {
-				log::error!("EVALUATION ERROR: {:?}", i);
-			}
+/// Items which were found, a trace of every node `items` matched and the pages fetched.
+pub async fn test_url_parser(url: &str, parser: &SearchParser, req_client: &Client) -> Result<(Vec<FoundItem>, Vec<NodeTrace>, usize)> {
+	let mut pages = Pages::new(parser.pagination.as_ref(), url);
+
+	let mut items = Vec::new();
+	let mut nodes = Vec::new();
+
+	let (mut final_url, mut resp) = http::get_body(req_client, url).await?;
+
+	while let Some(next) = parse_page(final_url, &resp, parser, &mut pages, &mut items, &mut nodes)? {
+		pages.wait().await;
+
+		(final_url, resp) = http::get_body(req_client, &next).await?;
+	}
 
-			i.ok()
-		})
-		.collect()
-	)
+	Ok((items, nodes, pages.count()))
+}
+
+/// Adds the items on the page. Returns the next page's URL.
+fn parse_page(final_url: Url, body: &str, parser: &SearchParser, pages: &mut Pages<'_>, items: &mut Vec<FoundItem>, nodes: &mut Vec<NodeTrace>) -> Result<Option<String>> {
+	let links = LinkResolver::new(final_url, body, parser.strip_tracking);
+	let page = Page::parse(body, parser.items_language)?;
+
+	let found = page.select_items(&parser.items)?;
+	let node_count = found.len();
+
+	for node in found {
+		let mut trace = NodeTrace::new(nodes.len());
+		trace.page = pages.count();
+
+		match evaluate_node(parser, &page, &node, &links, &mut trace) {
+			Ok(item) => items.push(item),
+			Err(e) => trace.fail(&e)
+		}
+
+		nodes.push(trace);
+	}
+
+	pages.next_url(&page, &links, node_count)
+}
+
+fn evaluate_node(parser: &SearchParser, page: &Page, node: &ItemNode<'_>, links: &LinkResolver, trace: &mut NodeTrace) -> Result<FoundItem> {
+	let title = trace.get_optional_string("title", Some(&parser.title), page, node)?;
+	let author = trace.get_optional_string("author", parser.author.as_ref(), page, node)?;
+
+	let content = match parser.content.as_ref() {
+		Some(opts) => trace.evaluate("content", Some(opts), page, node, true)?.map(|v| links.resolve_html(&v)),
+		None => None
+	};
+
+	let date = trace.get_optional_string("date", Some(&parser.date), page, node)?;
+
+	let timezone = parser.timezone.as_deref().and_then(date::parse_timezone);
+	let timestamp = date.as_deref().and_then(|v| date::parse_date(v, timezone, Utc::now()));
+
+	// Still an item. It's flagged when stored instead.
+	if timestamp.is_none() {
+		trace.flag("date", &Error::InvalidField("date".into(), format!("Couldn't parse \"{}\" as a date.", date.as_deref().unwrap_or_default())));
+	}
+	let guid = trace.get_optional_string("guid", Some(&parser.guid), page, node)?;
+	let link = trace.get_optional_string("link", Some(&parser.link), page, node)?;
+
+	Ok(FoundItem {
+		title: title.ok_or_else(|| Error::MissingField("title".into()))?,
+		link: link.map(|v| links.resolve(&v)).ok_or_else(|| Error::MissingField("link".into()))?,
+		guid: guid.ok_or_else(|| Error::MissingField("guid".into()))?,
+		date: date.unwrap_or_default(),
+		timestamp: timestamp.map(|v| v.timestamp()),
+
+		author,
+		content
+	})
 }
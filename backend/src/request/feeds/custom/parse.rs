@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use chrono::format::StrftimeItems;
+use regex::{Regex, RegexBuilder};
+use serde::{Serialize, Deserialize, Deserializer};
+
+use crate::{Error, Result};
+use super::date;
+
+
+const CURRENCY_SYMBOLS: &str = "$€£¥₹₽₩₺₪₫฿¢₴₦₱₡₲₵₸₼₾";
+
+
+/// A single step of a `ParseOpts` pipeline. Steps run in order on the selected value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Parse {
+	None,
+	// Expression
+	Regex(String),
+	// Format, Timezone if the value doesn't have one (PST, +02:00)
+	TimeFormat(String, Option<String>),
+
+	Trim,
+	/// Whitespace runs become a single space. Also trims.
+	CollapseWhitespace,
+	Lowercase,
+	// From, To
+	Replace(String, String),
+	/// Removes currency symbols and 3 letter codes. "1.299,00 €" -> "1.299,00"
+	StripCurrency,
+	/// Localized number to a plain one. "1.299,00" -> "1299.00"
+	/// Contains the decimal separator. It's guessed if None.
+	Number(Option<char>),
+	/// Joins the values of every node the selector found with the separator.
+	Join(String),
+
+	/// Has to be the first step. Reads the attribute of the selected node.
+	Attr(String),
+	/// Has to be the first step. Reads the text of the selected node.
+	Text
+}
+
+impl Default for Parse {
+	fn default() -> Self {
+		Parse::None
+	}
+}
+
+impl Parse {
+	pub fn name(&self) -> &'static str {
+		match self {
+			Parse::None => "None",
+			Parse::Regex(_) => "Regex",
+			Parse::TimeFormat(..) => "TimeFormat",
+			Parse::Trim => "Trim",
+			Parse::CollapseWhitespace => "CollapseWhitespace",
+			Parse::Lowercase => "Lowercase",
+			Parse::Replace(..) => "Replace",
+			Parse::StripCurrency => "StripCurrency",
+			Parse::Number(_) => "Number",
+			Parse::Join(_) => "Join",
+			Parse::Attr(_) => "Attr",
+			Parse::Text => "Text"
+		}
+	}
+
+	/// The expression of a `Regex` step. None for every other step.
+	pub fn compile(&self) -> Result<Option<Regex>> {
+		match self {
+			Parse::Regex(expr) => Ok(Some(RegexBuilder::new(expr).case_insensitive(true).build()?)),
+			_ => Ok(None)
+		}
+	}
+
+	pub fn validate(&self, field: &str) -> Result<()> {
+		match self {
+			Parse::Regex(expr) => {
+				let regex = RegexBuilder::new(expr)
+					.case_insensitive(true)
+					.build()
+					.map_err(|e| Error::InvalidField(field.into(), format!("Invalid regex: {}", e)))?;
+
+				// Group 1 is used as the value.
+				if regex.captures_len() < 2 {
+					return Err(Error::InvalidField(field.into(), "Regex needs a capture group.".into()));
+				}
+			}
+
+			Parse::TimeFormat(format, _) if format.trim().is_empty() || StrftimeItems::new(format).any(|v| v == chrono::format::Item::Error) => {
+				return Err(Error::InvalidField(field.into(), format!("Invalid time format \"{}\".", format)));
+			}
+
+			Parse::TimeFormat(_, Some(offset)) if date::parse_timezone(offset).is_none() => {
+				return Err(Error::InvalidField(field.into(), format!("Unknown timezone \"{}\".", offset)));
+			}
+
+			Parse::Replace(from, _) if from.is_empty() => {
+				return Err(Error::InvalidField(field.into(), "Replace needs something to replace.".into()));
+			}
+
+			Parse::Number(Some(sep)) if sep.is_ascii_digit() || *sep == '-' => {
+				return Err(Error::InvalidField(field.into(), format!("Invalid decimal separator \"{}\".", sep)));
+			}
+
+			Parse::Attr(name) if name.trim().is_empty() => {
+				return Err(Error::InvalidField(field.into(), "Attr needs an attribute name.".into()));
+			}
+
+			_ => ()
+		}
+
+		Ok(())
+	}
+
+	/// Transforms a single value. `Join`, `Attr` and `Text` are handled by `ParseOpts`.
+	/// `compiled` is the step's already compiled regex. It's compiled here if None.
+	pub fn apply(&self, value: String, compiled: Option<&Regex>) -> Result<String> {
+		Ok(
+			match self {
+				Parse::Regex(expr) => {
+					let regex = match compiled {
+						Some(v) => Cow::Borrowed(v),
+						None => Cow::Owned(RegexBuilder::new(expr).case_insensitive(true).build()?)
+					};
+
+					match regex.captures(&value).and_then(|v| v.get(1)) {
+						Some(found) => found.as_str().to_string(),
+						None => return Err(Error::Transform(self.name().into(), format!("\"{}\" didn't match \"{}\".", expr, value)))
+					}
+				}
+
+				Parse::TimeFormat(format, Some(offset)) => {
+					let offset = date::parse_timezone(offset)
+						.ok_or_else(|| Error::Transform(self.name().into(), format!("Unknown timezone \"{}\".", offset)))?;
+
+					match offset.from_local_datetime(&parse_naive(&value, format)?).single() {
+						Some(date) => date.to_rfc3339(),
+						None => return Err(Error::Transform(self.name().into(), format!("\"{}\" isn't a valid time.", value)))
+					}
+				}
+
+				// Without an offset the parser's timezone is used unless the format has one (%z).
+				Parse::TimeFormat(format, None) => match DateTime::parse_from_str(&value, format) {
+					Ok(date) => date.to_rfc3339(),
+					Err(_) => parse_naive(&value, format)?.format("%Y-%m-%dT%H:%M:%S").to_string()
+				},
+
+				Parse::Trim => value.trim().to_string(),
+				Parse::CollapseWhitespace => value.split_whitespace().collect::<Vec<_>>().join(" "),
+				Parse::Lowercase => value.to_lowercase(),
+				Parse::Replace(from, to) => value.replace(from.as_str(), to),
+				Parse::StripCurrency => strip_currency(&value),
+				Parse::Number(decimal) => parse_number(&value, *decimal).map_err(|e| Error::Transform(self.name().into(), e))?,
+
+				Parse::None | Parse::Join(_) | Parse::Attr(_) | Parse::Text => value
+			}
+		)
+	}
+}
+
+
+/// Old parsers stored a single step instead of a list.
+pub fn deserialize_steps<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<Parse>, D::Error> {
+	#[derive(Deserialize)]
+	#[serde(untagged)]
+	enum Steps {
+		Many(Vec<Parse>),
+		One(Parse)
+	}
+
+	let steps = match Steps::deserialize(deserializer)? {
+		Steps::Many(v) => v,
+		Steps::One(v) => vec![v]
+	};
+
+	Ok(steps.into_iter().filter(|v| *v != Parse::None).collect())
+}
+
+
+/// Formats without a time are at midnight.
+fn parse_naive(value: &str, format: &str) -> Result<NaiveDateTime> {
+	match NaiveDateTime::parse_from_str(value, format) {
+		Ok(v) => Ok(v),
+		Err(e) => NaiveDate::parse_from_str(value, format)
+			.map(|v| v.and_time(NaiveTime::MIN))
+			.map_err(|_| e.into())
+	}
+}
+
+fn strip_currency(value: &str) -> String {
+	let value = value.replace(|c| CURRENCY_SYMBOLS.contains(c), " ");
+
+	let is_code = |v: &str| v.len() == 3 && v.chars().all(|c| c.is_ascii_uppercase());
+
+	value.split_whitespace()
+		.map(|token| {
+			// Codes can be stuck to the number. ex. "USD12.00"
+			let start = token.find(|c: char| !c.is_ascii_uppercase()).unwrap_or(token.len());
+			let token = if is_code(&token[..start]) { &token[start..] } else { token };
+
+			let end = token.rfind(|c: char| !c.is_ascii_uppercase()).map(|v| v + 1).unwrap_or(0);
+			if is_code(&token[end..]) { &token[..end] } else { token }
+		})
+		.filter(|v| !v.is_empty())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+fn parse_number(value: &str, decimal: Option<char>) -> std::result::Result<String, String> {
+	let cleaned = value.chars()
+		.filter(|c| c.is_ascii_digit() || matches!(c, '-' | '.' | ',') || Some(*c) == decimal)
+		.collect::<String>();
+
+	let decimal = decimal.or_else(|| guess_decimal(&cleaned));
+
+	let mut number = String::new();
+
+	for c in cleaned.chars() {
+		match c {
+			'-' if number.is_empty() => number.push('-'),
+			c if c.is_ascii_digit() => number.push(c),
+			c if Some(c) == decimal => number.push('.'),
+			_ => ()
+		}
+	}
+
+	if number.parse::<f64>().is_err() {
+		return Err(format!("\"{}\" isn't a number.", value));
+	}
+
+	Ok(number)
+}
+
+/// The last separator is the decimal one unless it looks like it separates thousands.
+fn guess_decimal(value: &str) -> Option<char> {
+	let pos = value.rfind(['.', ','])?;
+	let sep = value[pos..].chars().next()?;
+	let other = if sep == '.' { ',' } else { '.' };
+
+	// Nothing to separate thousands of. ex. "0.250"
+	let integer = value[..pos].trim_start_matches('-');
+
+	if value.contains(other) || integer.is_empty() || integer == "0" {
+		Some(sep)
+	} else if value.matches(sep).count() > 1 || value.len() - pos - 1 == 3 {
+		None
+	} else {
+		Some(sep)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Deserialize)]
+	struct Stored {
+		#[serde(deserialize_with = "deserialize_steps")]
+		parse_type: Vec<Parse>
+	}
+
+	fn steps(json: &str) -> Vec<Parse> {
+		serde_json::from_str::<Stored>(&format!(r#"{{"parse_type":{}}}"#, json)).unwrap().parse_type
+	}
+
+	#[test]
+	fn localized_numbers() {
+		assert_eq!(parse_number(&strip_currency("1.299,00 €"), None).as_deref(), Ok("1299.00"));
+		assert_eq!(parse_number("$1,299.00", None).as_deref(), Ok("1299.00"));
+		assert_eq!(parse_number("1,299", None).as_deref(), Ok("1299"));
+		assert_eq!(parse_number("-3,5", None).as_deref(), Ok("-3.5"));
+		assert_eq!(parse_number("0.250 €", None).as_deref(), Ok("0.250"));
+		assert_eq!(parse_number("1 299'5", Some('\'')).as_deref(), Ok("1299.5"));
+		assert!(parse_number("Sold out", None).is_err());
+	}
+
+	#[test]
+	fn decimal_separator() {
+		assert_eq!(guess_decimal("1.299,00"), Some(','));
+		assert_eq!(guess_decimal("1,299.00"), Some('.'));
+		assert_eq!(guess_decimal("12,5"), Some(','));
+		assert_eq!(guess_decimal("1.299"), None);
+		assert_eq!(guess_decimal("1.299.000"), None);
+		assert_eq!(guess_decimal("1299"), None);
+		assert_eq!(guess_decimal("0.299"), Some('.'));
+		assert_eq!(guess_decimal("0,250"), Some(','));
+		assert_eq!(guess_decimal(".250"), Some('.'));
+	}
+
+	#[test]
+	fn currency() {
+		assert_eq!(strip_currency("1.299,00 €"), "1.299,00");
+		assert_eq!(strip_currency("£ 12.50"), "12.50");
+		assert_eq!(strip_currency("USD12.00"), "12.00");
+		assert_eq!(strip_currency("12.00 EUR"), "12.00");
+	}
+
+	#[test]
+	fn stored_steps() {
+		assert_eq!(steps(r#""None""#), Vec::new());
+		assert_eq!(steps(r#"{"Regex":"(\\d+)"}"#), vec![Parse::Regex(r"(\d+)".into())]);
+		assert_eq!(steps(r#"{"TimeFormat":["%d.%m.%Y",null]}"#), vec![Parse::TimeFormat("%d.%m.%Y".into(), None)]);
+		assert_eq!(steps(r#"["Trim",{"Number":null},"None"]"#), vec![Parse::Trim, Parse::Number(None)]);
+	}
+
+	#[test]
+	fn regex_step() {
+		let step = Parse::Regex(r"price: (\d+)".into());
+		let compiled = step.compile().unwrap();
+
+		assert_eq!(step.apply("Price: 12".into(), compiled.as_ref()).unwrap(), "12");
+		assert_eq!(step.apply("Price: 12".into(), None).unwrap(), "12");
+		assert!(step.apply("Free".into(), compiled.as_ref()).is_err());
+	}
+}
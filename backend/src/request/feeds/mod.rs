@@ -1,25 +1,31 @@
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use diesel::{RunQueryDsl, SqliteConnection};
 use reqwest::Client;
 
 use crate::error::{Error, Result};
-use crate::feature::objects::get_listeners;
+use crate::metrics;
+use crate::feature::objects::{get_listeners, get_parser_runs, update_listener_feed_type};
 use crate::feature::schema::{items as ItemsSchema, feeds as FeedsSchema};
 use crate::feature::models::{QueryId, NewFeedItemModel, FeedModel, NewFeedModel};
-use super::{RequestResults, ItemResults, RequestItemResults, InnerRequestResults};
+use super::{RequestResults, ItemResults, RequestItemResults, InnerRequestResults, BASELINE_RUNS};
 
 pub mod rss;
 pub mod atom;
 pub mod custom;
+pub mod sitemap;
 
 type CollectedResult = Result<RequestItemResults<NewFeedItemModel>>;
 
+/// Feeds added without being fetched (ex. from OPML). The first request detects the type.
+pub const UNDETECTED_FEED_TYPE: i32 = -1;
+
 
 pub enum FeedType {
 	Rss(rss::FeedResult),
 	Atom(atom::FeedResult),
 	Custom(custom::CustomResult),
+	Sitemap(sitemap::FeedResult),
 
 	__Unknown
 }
@@ -37,6 +43,9 @@ impl FeedType {
 
 					if let InvalidStartTag = e {
 						// TODO: Fix this so it's not an if else
+					} else if sitemap::detect(url, req_client).await.is_ok() {
+						// Gzipped sitemaps fail to decode as RSS.
+						return FeedType::Sitemap(Ok(Vec::new()));
 					} else {
 						return FeedType::Rss(Err(Error::Rss(e)));
 					}
@@ -66,6 +75,12 @@ impl FeedType {
 			}
 		}
 
+		// SITEMAP - Only the root element is checked. Its' URLs are fetched on the first request.
+		match sitemap::detect(url, req_client).await {
+			Ok(()) => return FeedType::Sitemap(Ok(Vec::new())),
+			Err(e) => log::debug!("sitemap: {:?}", e)
+		}
+
 		// CUSTOM
 		match custom::get_from_url(url, req_client, conn).await {
 			Ok(i) => FeedType::Custom(Ok(i)),
@@ -82,6 +97,7 @@ impl FeedType {
 			0 => FeedType::Rss(rss::get_from_url(url, req_client).await),
 			1 => FeedType::Atom(atom::get_from_url(url, req_client).await),
 			2 => FeedType::Custom(custom::get_from_url(url, req_client, conn).await),
+			3 => FeedType::Sitemap(sitemap::get_from_url(url, None, req_client).await),
 			_ => FeedType::__Unknown
 		}
 	}
@@ -106,8 +122,10 @@ impl RequestManager {
 			FeedType::Rss(Ok(feed)) => rss::new_from_feed(url, feed),
 			FeedType::Atom(Ok(feed)) => atom::new_from_feed(url, feed),
 			FeedType::Custom(Ok(_)) => custom::new_from_url(url, custom_item_id, conn)?,
+			FeedType::Sitemap(Ok(_)) => sitemap::new_from_url(url)?,
 
-			FeedType::Custom(Err(e))
+			FeedType::Sitemap(Err(e))
+			| FeedType::Custom(Err(e))
 			| FeedType::Atom(Err(e))
 			| FeedType::Rss(Err(e)) => return Err(e),
 
@@ -151,9 +169,17 @@ impl RequestManager {
 
 		for feed in &feeds {
 			let cloned_feed = (*feed).clone();
+			let source = metrics::feed_source(feed.feed_type);
+
+			metrics::record_scheduler_lag(source, chrono::Utc::now().timestamp() - feed.last_called - feed.sec_interval as i64);
+
+			let started = Instant::now();
+			let feed_results = request_feed(&cloned_feed, req_client, connection).await;
+
+			metrics::record_fetch(source, feed.id, started.elapsed(), feed_results.as_ref().err());
 
 			results.items.push(ItemResults {
-				results: request_feed(&cloned_feed, req_client, connection).await,
+				results: feed_results,
 				item: cloned_feed
 			});
 		}
@@ -167,6 +193,8 @@ impl RequestManager {
 
 			// After finished insert new items to DB.
 			for res in results.items.iter_mut() {
+				let feed_id = res.item.id;
+
 				if let Ok(res) = res.results.as_mut() {
 					let e = diesel::insert_or_ignore_into(ItemsSchema::table)
 						.values(&res.to_insert)
@@ -174,6 +202,8 @@ impl RequestManager {
 
 					if let Ok(count) = e {
 						res.new_item_count = count;
+
+						metrics::record_items_inserted(feed_id, count);
 					}
 				}
 			}
@@ -191,15 +221,53 @@ impl RequestManager {
 pub async fn request_feed(feed: &FeedModel, req_client: &Client, conn: &SqliteConnection) -> CollectedResult {
 	log::debug!(" - Requesting: {}", feed.url);
 
+	let detected;
+
+	let feed = if feed.feed_type == UNDETECTED_FEED_TYPE {
+		detected = FeedModel {
+			feed_type: detect_feed_type(&feed.url, req_client, conn).await?,
+			..feed.clone()
+		};
+
+		update_listener_feed_type(feed.id, detected.feed_type, conn)?;
+
+		&detected
+	} else {
+		feed
+	};
+
 	let mut feed_res = RequestItemResults {
 		start_time: SystemTime::now(),
 		duration: Duration::new(0, 0),
 		new_item_count: 0,
 		item_count: 0,
-		to_insert: Vec::new()
+		to_insert: Vec::new(),
+		parser_stats: None
+	};
+
+	let feed_type = if feed.feed_type == 2 {
+		let (items, mut stats) = custom::get_from_url_with_stats(&feed.url, req_client, conn).await?;
+
+		stats.check_degraded(&get_parser_runs(Some(feed.id), None, BASELINE_RUNS, conn)?);
+
+		if let Some(reason) = stats.degraded.as_ref() {
+			log::warn!(r#"Feed "{}" parser is degraded: {}"#, feed.title, reason);
+		} else if let Some(reason) = stats.fallback_reason() {
+			log::warn!(r#"Feed "{}" parser primary selector failed: {}"#, feed.title, reason);
+		}
+
+		feed_res.parser_stats = Some(stats);
+
+		FeedType::Custom(Ok(items))
+	} else if feed.feed_type == 3 {
+		let filter = feed.url_filter.as_deref().map(sitemap::parse_filter).transpose()?;
+
+		FeedType::Sitemap(sitemap::get_from_url(&feed.url, filter.as_ref(), req_client).await)
+	} else {
+		FeedType::req_from_feed_type(feed.feed_type, &feed.url, req_client, conn).await
 	};
 
-	match FeedType::req_from_feed_type(feed.feed_type, &feed.url, req_client, conn).await {
+	match feed_type {
 		FeedType::Rss(Ok(channel)) => {
 			feed_res.to_insert = channel.items()
 			.iter()
@@ -231,9 +299,27 @@ pub async fn request_feed(feed: &FeedModel, req_client: &Client, conn: &SqliteCo
 				item
 			})
 			.collect();
+
+			let unparsed = feed_res.to_insert.iter().filter(|v| v.date_unparsed).count();
+
+			if unparsed != 0 {
+				log::warn!(r#"Feed "{}" has {} items with dates which couldn't be parsed."#, feed.title, unparsed);
+			}
+		}
+
+		FeedType::Sitemap(Ok(urls)) => {
+			feed_res.to_insert = urls
+			.iter()
+			.map(|i| {
+				let mut item: NewFeedItemModel = i.into();
+				item.feed_id = feed.id;
+				item
+			})
+			.collect();
 		}
 
 		FeedType::Atom(Err(e))
+		| FeedType::Sitemap(Err(e))
 		// | FeedType::Custom(Err(e))
 		| FeedType::Rss(Err(e)) => return Err(e),
 
@@ -246,6 +332,23 @@ pub async fn request_feed(feed: &FeedModel, req_client: &Client, conn: &SqliteCo
 }
 
 
+async fn detect_feed_type(url: &str, req_client: &Client, conn: &SqliteConnection) -> Result<i32> {
+	Ok(match FeedType::from_url(url, req_client, conn).await {
+		FeedType::Rss(Ok(_)) => 0,
+		FeedType::Atom(Ok(_)) => 1,
+		FeedType::Custom(Ok(_)) => 2,
+		FeedType::Sitemap(Ok(_)) => 3,
+
+		FeedType::Sitemap(Err(e))
+		| FeedType::Custom(Err(e))
+		| FeedType::Atom(Err(e))
+		| FeedType::Rss(Err(e)) => return Err(e),
+
+		FeedType::__Unknown => return Err("Unknown Feed.. It didn't match the current supported ones.".into())
+	})
+}
+
+
 pub fn update_feed_last_called_db(set_last_called: i64, feeds_arr: Vec<FeedModel>, connection: &SqliteConnection) {
 	use diesel::prelude::*;
 	use FeedsSchema::dsl::*;
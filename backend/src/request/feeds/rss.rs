@@ -1,6 +1,6 @@
 use reqwest::Client;
 
-use crate::Result;
+use crate::{Result, http};
 
 use super::NewFeedModel;
 
@@ -28,11 +28,13 @@ pub fn new_from_feed(url: String, feed: rss::Channel) -> NewFeedModel {
 
 		date_added: chrono::Utc::now().naive_utc().timestamp(),
 		last_called: chrono::Utc::now().naive_utc().timestamp(),
+
+		url_filter: None
 	}
 }
 
 
 pub async fn get_from_url(url: &str, req_client: &Client) -> Result<rss::Channel> {
-	let resp = req_client.get(url).send().await?.bytes().await?;
+	let resp = http::get(req_client, url).await?.bytes().await?;
 	Ok(rss::Channel::read_from(&resp[..])?)
 }
@@ -0,0 +1,242 @@
+// Sitemaps and sitemap indexes (https://www.sitemaps.org/protocol.html).
+// Every URL is an item so only ones which weren't seen before are inserted.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use reqwest::Client;
+use url::Url;
+
+use crate::{Error, Result, http};
+use crate::pattern::UrlPattern;
+
+use super::NewFeedModel;
+
+
+/// Most sitemaps fetched for a feed, including the first one.
+const MAX_SITEMAPS: usize = 50;
+
+/// Largest a gzipped sitemap can be once decompressed. The protocol's limit is 50 MB.
+const MAX_DECODED_SIZE: u64 = 50 * 1024 * 1024;
+
+
+pub type FeedResult = Result<Vec<SitemapUrl>>;
+
+
+/// A `<url>` entry.
+#[derive(Debug, Clone, Default)]
+pub struct SitemapUrl {
+	pub loc: String,
+	pub lastmod: Option<String>
+}
+
+impl SitemapUrl {
+	/// Last path segment with separators as spaces. ex. "blue shirt" for `/products/blue-shirt`.
+	pub fn title(&self) -> String {
+		let url = match Url::parse(&self.loc) {
+			Ok(v) => v,
+			Err(_) => return self.loc.clone()
+		};
+
+		url.path_segments()
+			.and_then(|mut v| v.rfind(|v| !v.is_empty()))
+			.map(|v| v.trim_end_matches(".html").replace(&['-', '_'][..], " "))
+			.unwrap_or_else(|| url.host_str().unwrap_or_default().to_string())
+	}
+}
+
+enum Sitemap {
+	Urls(Vec<SitemapUrl>),
+	/// Locations of nested sitemaps.
+	Index(Vec<String>)
+}
+
+
+pub fn new_from_url(url: String) -> Result<NewFeedModel> {
+	let title = format!("{} sitemap", Url::parse(&url)?.host_str().unwrap_or_default());
+
+	Ok(NewFeedModel {
+		url,
+
+		enabled: true,
+
+		title,
+		description: String::new(),
+		generator: String::new(),
+
+		feed_type: 3,
+
+		// Sitemaps are large and don't change often.
+		sec_interval: 60 * 60,
+		remove_after: 0,
+
+		global_show: true,
+		ignore_if_not_new: true,
+
+		date_added: chrono::Utc::now().timestamp(),
+		last_called: chrono::Utc::now().timestamp(),
+
+		url_filter: None
+	})
+}
+
+/// A feed's `url_filter`. Same syntax as a parser's `match_url`. ex. `*/products/*`
+pub fn parse_filter(value: &str) -> Result<UrlPattern> {
+	UrlPattern::parse(value).map_err(|e| match e {
+		Error::InvalidField(_, reason) => Error::InvalidField("url_filter".into(), reason),
+		e => e
+	})
+}
+
+
+/// Checks the root element is `<urlset>` or `<sitemapindex>`. Nested sitemaps aren't fetched.
+pub async fn detect(url: &str, req_client: &Client) -> Result<()> {
+	let body = http::get(req_client, url).await?.bytes().await?;
+	let body = decode(&body)?;
+
+	let mut reader = Reader::from_reader(&body[..]);
+	let mut buf = Vec::new();
+
+	loop {
+		match reader.read_event(&mut buf)? {
+			Event::Start(e) => return match e.name() {
+				b"urlset" | b"sitemapindex" => Ok(()),
+				_ => Err(Error::Other("Not a sitemap. Expected <urlset> or <sitemapindex>.".into()))
+			},
+
+			Event::Eof => return Err(Error::Other("Not a sitemap. It's empty.".into())),
+
+			_ => ()
+		}
+
+		buf.clear();
+	}
+}
+
+/// URLs in the sitemap and every sitemap nested in it. Only ones matching `filter` if there's one.
+/// Nested sitemaps which can't be fetched are skipped.
+pub async fn get_from_url(url: &str, filter: Option<&UrlPattern>, req_client: &Client) -> FeedResult {
+	let mut pending = vec![url.to_string()];
+	let mut visited: Vec<String> = Vec::new();
+
+	let mut urls = Vec::new();
+
+	while !pending.is_empty() {
+		let next = pending.remove(0);
+
+		if visited.contains(&next) {
+			continue;
+		}
+
+		if visited.len() == MAX_SITEMAPS {
+			log::warn!("Sitemap {} has more than {} nested sitemaps. Skipping the rest.", url, MAX_SITEMAPS);
+			break;
+		}
+
+		let found = match http::get(req_client, &next).await {
+			Ok(resp) => resp.bytes().await.map_err(Error::from).and_then(|v| parse(&v)),
+			Err(e) => Err(e)
+		};
+
+		visited.push(next);
+
+		match found {
+			Ok(Sitemap::Urls(found)) => urls.extend(found.into_iter().filter(|v| is_match(filter, &v.loc))),
+			Ok(Sitemap::Index(found)) => pending.extend(found),
+
+			Err(e) if visited.len() == 1 => return Err(e),
+			Err(e) => log::error!("sitemap {}: {:?}", visited[visited.len() - 1], e)
+		}
+	}
+
+	Ok(urls)
+}
+
+
+fn parse(body: &[u8]) -> Result<Sitemap> {
+	let body = decode(body)?;
+
+	let mut reader = Reader::from_reader(&body[..]);
+	reader.trim_text(true);
+
+	let mut is_index = None;
+
+	let mut urls = Vec::new();
+	let mut sitemaps = Vec::new();
+
+	let mut current = SitemapUrl::default();
+	// Element the text is for. Prefixed ones (ex. image:loc) are ignored.
+	let mut field: Option<Vec<u8>> = None;
+
+	let mut buf = Vec::new();
+
+	loop {
+		match reader.read_event(&mut buf)? {
+			Event::Start(e) => match (is_index, e.name()) {
+				(None, b"urlset") => is_index = Some(false),
+				(None, b"sitemapindex") => is_index = Some(true),
+				(None, _) => return Err(Error::Other("Not a sitemap. Expected <urlset> or <sitemapindex>.".into())),
+
+				(_, b"url") | (_, b"sitemap") => current = SitemapUrl::default(),
+				(_, name) => field = Some(name.to_vec())
+			},
+
+			// quick-xml escapes CDATA as well.
+			Event::Text(e) | Event::CData(e) => {
+				let value = e.unescape_and_decode(&reader)?;
+
+				match field.as_deref() {
+					Some(b"loc") => current.loc = value,
+					Some(b"lastmod") => current.lastmod = Some(value),
+					_ => ()
+				}
+			}
+
+			Event::End(e) => match e.name() {
+				b"url" if !current.loc.is_empty() => urls.push(std::mem::take(&mut current)),
+				b"sitemap" if !current.loc.is_empty() => sitemaps.push(std::mem::take(&mut current).loc),
+				_ => field = None
+			},
+
+			Event::Eof => break,
+
+			_ => ()
+		}
+
+		buf.clear();
+	}
+
+	match is_index {
+		Some(true) => Ok(Sitemap::Index(sitemaps)),
+		Some(false) => Ok(Sitemap::Urls(urls)),
+		None => Err(Error::Other("Not a sitemap. It's empty.".into()))
+	}
+}
+
+/// .xml.gz files are usually served without a Content-Encoding so they aren't decoded for us.
+fn decode(body: &[u8]) -> Result<Cow<'_, [u8]>> {
+	if !body.starts_with(&[0x1f, 0x8b]) {
+		return Ok(Cow::Borrowed(body));
+	}
+
+	let mut decoded = Vec::new();
+
+	// One byte over the limit to tell if it was reached.
+	GzDecoder::new(body).take(MAX_DECODED_SIZE + 1).read_to_end(&mut decoded)?;
+
+	if decoded.len() as u64 > MAX_DECODED_SIZE {
+		return Err(Error::Other(format!("Sitemap is larger than {} MB decompressed.", MAX_DECODED_SIZE / 1024 / 1024)));
+	}
+
+	Ok(Cow::Owned(decoded))
+}
+
+fn is_match(filter: Option<&UrlPattern>, loc: &str) -> bool {
+	match filter {
+		Some(filter) => Url::parse(loc).map(|v| filter.is_match(&v)).unwrap_or_default(),
+		None => true
+	}
+}
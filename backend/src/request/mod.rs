@@ -1,16 +1,22 @@
+use std::collections::BTreeMap;
 use std::time::{Duration, SystemTime};
 
 use reqwest::header::HeaderMap;
 
 use crate::feature::models::{
 	FeedModel, NewFeedItemModel,
-	WatchingModel, NewWatchHistoryModel
+	WatchingModel, NewWatchHistoryModel,
+	RequestHistoryItemModel
 };
 
 use crate::Result;
+use crate::xpath::NodeTrace;
 
 pub mod feeds;
 pub mod watcher;
+pub mod structured;
+pub mod pagination;
+pub mod page_text;
 
 
 #[derive(Debug)]
@@ -36,7 +42,105 @@ pub struct RequestItemResults<I> {
 	pub duration: Duration,
 	pub new_item_count: usize,
 	pub item_count: i32,
-	pub to_insert: Vec<I>
+	pub to_insert: Vec<I>,
+	/// Only for parsers and structured data.
+	pub parser_stats: Option<ParserStats>
+}
+
+
+/// Below this share of the usual item count a parser is degraded.
+const DEGRADED_RATIO: f64 = 0.5;
+/// Previous runs used for the usual item count.
+pub const BASELINE_RUNS: i64 = 10;
+
+/// How well a parser did on a single run.
+#[derive(Debug, Clone, Default)]
+pub struct ParserStats {
+	/// Nodes matched by `items`.
+	pub node_count: usize,
+	/// Nodes which became items.
+	pub found_count: usize,
+	/// Required field -> nodes it failed on.
+	pub field_failures: BTreeMap<String, usize>,
+	/// Field -> nodes where only a fallback selector gave a value.
+	pub fallback_fields: BTreeMap<String, usize>,
+	/// Pages fetched. More than one with pagination.
+	pub page_count: usize,
+
+	/// Why the parser looks broken.
+	pub degraded: Option<String>,
+	/// The previous run wasn't degraded.
+	pub newly_degraded: bool
+}
+
+impl ParserStats {
+	pub fn new(nodes: &[NodeTrace], page_count: usize) -> Self {
+		let mut field_failures = BTreeMap::new();
+
+		let mut fallback_fields = BTreeMap::new();
+
+		for field in nodes.iter().filter_map(|v| v.failed_field.as_ref()) {
+			*field_failures.entry(field.clone()).or_default() += 1;
+		}
+
+		for field in nodes.iter().flat_map(|v| &v.fields).filter(|v| v.fallback.is_some()) {
+			*fallback_fields.entry(field.field.clone()).or_default() += 1;
+		}
+
+		Self {
+			node_count: nodes.len(),
+			found_count: nodes.iter().filter(|v| v.error.is_none()).count(),
+			field_failures,
+			fallback_fields,
+			page_count,
+			..Self::default()
+		}
+	}
+
+	/// Set if a primary selector stopped giving values and fallbacks are used instead.
+	pub fn fallback_reason(&self) -> Option<String> {
+		if self.fallback_fields.is_empty() {
+			return None;
+		}
+
+		let fields = self.fallback_fields.iter()
+			.map(|(field, count)| format!("{} on {}", field, count))
+			.collect::<Vec<_>>()
+			.join(", ");
+
+		Some(format!("Using fallback selectors ({}).", fields))
+	}
+
+	/// Compares against previous runs, newest first.
+	pub fn check_degraded(&mut self, previous: &[RequestHistoryItemModel]) {
+		let healthy = previous.iter()
+			.filter(|v| v.degraded.is_none())
+			.filter_map(|v| v.found_count)
+			.collect::<Vec<_>>();
+
+		let baseline = if healthy.is_empty() {
+			None
+		} else {
+			Some(healthy.iter().sum::<i32>() as f64 / healthy.len() as f64)
+		};
+
+		self.degraded = if self.node_count == 0 {
+			Some("No nodes matched the items selector.".to_string())
+		} else if self.found_count == 0 {
+			let failures = self.field_failures.iter()
+				.map(|(field, count)| format!("{} on {}", field, count))
+				.collect::<Vec<_>>()
+				.join(", ");
+
+			Some(format!("All {} nodes failed ({}).", self.node_count, failures))
+		} else {
+			baseline
+				.filter(|usual| (self.found_count as f64) < usual * DEGRADED_RATIO)
+				.map(|usual| format!("Found {} items, usually about {:.0}.", self.found_count, usual))
+		};
+
+		self.newly_degraded = self.degraded.is_some() && previous.first().map(|v| v.degraded.is_none()).unwrap_or(true);
+	}
 }
 
 
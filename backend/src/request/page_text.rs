@@ -0,0 +1,162 @@
+// Watching a page's visible text without a parser.
+// Changes are stored as a line diff of the text.
+
+use regex::Regex;
+use scraper::{ElementRef, Html};
+use serde::{Serialize, Deserialize};
+use similar::TextDiff;
+
+use crate::{Error, Result};
+use crate::xpath::NodeTrace;
+use crate::selector::{self, Page, SelectorLanguage};
+use super::watcher::FoundItem;
+
+
+/// Elements which never show text.
+const HIDDEN: [&str; 7] = ["head", "script", "style", "noscript", "template", "svg", "iframe"];
+
+/// Elements which start a new line.
+const BLOCKS: [&str; 33] = [
+	"address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "fieldset", "figcaption", "figure",
+	"footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav", "ol", "p", "pre",
+	"section", "table", "td", "th", "tr"
+];
+
+
+/// Watches the page's visible text instead of parsed items. Stored as JSON in `watching.page_text`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageText {
+	/// Only the text of the first element this selects. The whole page if None.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub selector: Option<String>,
+	/// XPath or CSS.
+	#[serde(default)]
+	pub language: SelectorLanguage,
+
+	/// Regexes removed from each line before comparing. ex. timestamps or visitor counters.
+	#[serde(default)]
+	pub ignore: Vec<String>
+}
+
+impl PageText {
+	pub fn validate(&self) -> Result<()> {
+		if self.language == SelectorLanguage::JsonPath {
+			return Err(Error::InvalidField("page_text".into(), "Selector has to be XPath or CSS.".into()));
+		}
+
+		if let Some(value) = self.selector.as_deref() {
+			selector::validate("page_text", value, self.language, self.language)?;
+		}
+
+		self.ignore_patterns().map(|_| ())
+	}
+
+	/// Normalized visible text. One line per block element with whitespace collapsed.
+	pub fn extract(&self, body: &str) -> Result<String> {
+		let html = match self.selector.as_deref() {
+			Some(value) => match Page::parse(body, self.language)?.select_html(value)? {
+				Some(found) => Html::parse_fragment(&found),
+				None => return Err(Error::MissingField("value".into()))
+			},
+
+			None => Html::parse_document(body)
+		};
+
+		let mut text = String::new();
+		push_text(html.root_element(), false, &mut text);
+
+		let ignore = self.ignore_patterns()?;
+
+		let lines = text.lines()
+			.map(|line| {
+				let mut line = collapse_whitespace(line);
+
+				for pattern in &ignore {
+					line = pattern.replace_all(&line, "").into_owned();
+				}
+
+				collapse_whitespace(&line)
+			})
+			.filter(|v| !v.is_empty())
+			.collect::<Vec<_>>();
+
+		if lines.is_empty() {
+			return Err(Error::MissingField("value".into()));
+		}
+
+		Ok(lines.join("\n"))
+	}
+
+	fn ignore_patterns(&self) -> Result<Vec<Regex>> {
+		self.ignore.iter()
+			.map(|v| Regex::new(v).map_err(|e| Error::InvalidField("page_text".into(), format!("Invalid ignore pattern: {}", e))))
+			.collect()
+	}
+}
+
+
+/// The page text as a single item, with a trace of it. No items if it couldn't be found.
+pub fn find_items(body: &str, opts: &PageText) -> (Vec<FoundItem>, Vec<NodeTrace>) {
+	let mut trace = NodeTrace::new(0);
+
+	match opts.extract(body) {
+		Ok(text) => {
+			let value = trace.record("value", opts.selector.as_deref().unwrap_or("page text"), Some(text)).unwrap_or_default();
+
+			(vec![FoundItem { value, ..FoundItem::default() }], vec![trace])
+		}
+
+		Err(e) => {
+			trace.fail(&e);
+
+			(Vec::new(), vec![trace])
+		}
+	}
+}
+
+/// Unified line diff from the old text to the new one.
+pub fn diff(old: &str, new: &str) -> String {
+	// Both end in a newline so the last line doesn't show up as changed.
+	let (old, new) = (format!("{}\n", old), format!("{}\n", new));
+
+	TextDiff::from_lines(&old, &new)
+		.unified_diff()
+		.context_radius(2)
+		.to_string()
+}
+
+
+/// Line breaks in the HTML source are only kept inside `pre`.
+fn push_text(element: ElementRef<'_>, in_pre: bool, text: &mut String) {
+	for child in element.children() {
+		if let Some(value) = child.value().as_text() {
+			if in_pre {
+				text.push_str(value);
+			} else {
+				text.push_str(&value.replace(&['\r', '\n'][..], " "));
+			}
+		} else if let Some(child) = ElementRef::wrap(child) {
+			let name = child.value().name();
+
+			if HIDDEN.contains(&name) || child.value().attr("hidden").is_some() {
+				continue;
+			}
+
+			let block = BLOCKS.contains(&name);
+
+			if block {
+				text.push('\n');
+			}
+
+			push_text(child, in_pre || name == "pre", text);
+
+			if block {
+				text.push('\n');
+			}
+		}
+	}
+}
+
+fn collapse_whitespace(value: &str) -> String {
+	value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,129 @@
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Error, Result};
+use crate::links::LinkResolver;
+use crate::selector::{self, Page, SelectorLanguage};
+
+
+/// Most pages a parser can follow.
+const MAX_PAGES: usize = 50;
+
+/// Longest wait between pages.
+const MAX_DELAY_MS: u64 = 10_000;
+
+/// Longest a single run can keep following pages. Requests hold the core lock the whole time.
+const MAX_DURATION: Duration = Duration::from_secs(60);
+
+
+/// Following pages which are parsed after the feed or watcher URL. Items from every page are merged.
+/// Either `next` or `url_template` is used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Pagination {
+	/// Selects the next page link from the whole page. Same language as `items`.
+	/// ex. `//a[@rel="next"]/@href` or `a.next::attr(href)`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub next: Option<String>,
+	/// `{page}` is replaced with the page number. The feed or watcher URL is page 1.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url_template: Option<String>,
+
+	/// Pages fetched including the first.
+	#[serde(default = "default_max_pages")]
+	pub max_pages: usize,
+	/// Milliseconds to wait before requesting the next page.
+	#[serde(default)]
+	pub delay_ms: u64
+}
+
+impl Pagination {
+	pub fn validate(&self, items_language: SelectorLanguage) -> Result<()> {
+		match (self.next.as_deref(), self.url_template.as_deref()) {
+			(Some(next), None) => selector::validate("pagination", next, items_language, items_language)?,
+
+			(None, Some(template)) if !template.contains("{page}") => {
+				return Err(Error::InvalidField("pagination".into(), "URL template needs a {page} placeholder.".into()));
+			}
+
+			(None, Some(_)) => (),
+
+			_ => return Err(Error::InvalidField("pagination".into(), "Set either next or url_template.".into()))
+		}
+
+		if self.max_pages == 0 || self.max_pages > MAX_PAGES {
+			return Err(Error::InvalidField("pagination".into(), format!("max_pages has to be between 1 and {}.", MAX_PAGES)));
+		}
+
+		if self.delay_ms > MAX_DELAY_MS {
+			return Err(Error::InvalidField("pagination".into(), format!("delay_ms can't be over {}.", MAX_DELAY_MS)));
+		}
+
+		Ok(())
+	}
+}
+
+
+/// Pages fetched for a single run.
+pub struct Pages<'a> {
+	pagination: Option<&'a Pagination>,
+	visited: Vec<String>,
+	started: Instant
+}
+
+impl<'a> Pages<'a> {
+	/// `url` is the first page.
+	pub fn new(pagination: Option<&'a Pagination>, url: &str) -> Self {
+		Self {
+			pagination,
+			visited: vec![url.to_string()],
+			started: Instant::now()
+		}
+	}
+
+	/// Pages fetched so far. Also the number of the current page.
+	pub fn count(&self) -> usize {
+		self.visited.len()
+	}
+
+	/// URL of the page after the current one. None if there's no next page, the current one had no nodes,
+	/// `max_pages` was reached or the run took too long.
+	pub fn next_url(&mut self, page: &Page, links: &LinkResolver, node_count: usize) -> Result<Option<String>> {
+		let pagination = match self.pagination {
+			Some(v) if node_count != 0 && self.count() < v.max_pages => v,
+			_ => return Ok(None)
+		};
+
+		if self.started.elapsed() >= MAX_DURATION {
+			log::warn!("Stopped following pages of {} after {} pages. It took over {}s.", self.visited[0], self.count(), MAX_DURATION.as_secs());
+			return Ok(None);
+		}
+
+		let found = match (pagination.next.as_deref(), pagination.url_template.as_deref()) {
+			(Some(next), _) => page.select_value(next)?.filter(|v| !v.trim().is_empty()),
+			(None, Some(template)) => Some(template.replace("{page}", &(self.count() + 1).to_string())),
+			(None, None) => None
+		};
+
+		// Stops sites which link back to a page we already have from looping.
+		let url = found.map(|v| links.resolve(&v)).filter(|v| !self.visited.contains(v));
+
+		if let Some(url) = url.as_ref() {
+			self.visited.push(url.clone());
+		}
+
+		Ok(url)
+	}
+
+	pub async fn wait(&self) {
+		// Stored parsers from before the limit can have a longer delay.
+		if let Some(delay) = self.pagination.map(|v| v.delay_ms.min(MAX_DELAY_MS)).filter(|v| *v != 0) {
+			tokio::time::sleep(Duration::from_millis(delay)).await;
+		}
+	}
+}
+
+
+fn default_max_pages() -> usize {
+	5
+}
@@ -0,0 +1,299 @@
+// schema.org Product / Offer data embedded in shop pages.
+// JSON-LD is used if the page has any Products, otherwise microdata.
+
+use std::str::FromStr;
+
+use scraper::{ElementRef, Html, Selector};
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+
+use crate::{Error, Result};
+use crate::xpath::NodeTrace;
+use super::watcher::FoundItem;
+
+
+/// What an Offer is watched for. Stored in `watching.structured_data`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StructuredValue {
+	/// `price` (or `lowPrice`) with `priceCurrency`. ex. "9.99 USD"
+	Price,
+	/// `availability` without the schema.org prefix. ex. "InStock"
+	Availability
+}
+
+impl StructuredValue {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			StructuredValue::Price => "price",
+			StructuredValue::Availability => "availability"
+		}
+	}
+}
+
+impl FromStr for StructuredValue {
+	type Err = Error;
+
+	fn from_str(value: &str) -> Result<Self> {
+		match value {
+			"price" => Ok(StructuredValue::Price),
+			"availability" => Ok(StructuredValue::Availability),
+			_ => Err(Error::InvalidField("structured_data".into(), format!("Expected price or availability, got \"{}\".", value)))
+		}
+	}
+}
+
+
+/// An Offer with the Product it's for. Either from JSON-LD or microdata.
+struct Offer {
+	source: &'static str,
+
+	name: Option<String>,
+	url: Option<String>,
+	unique_id: Option<String>,
+
+	price: Option<String>,
+	currency: Option<String>,
+	availability: Option<String>
+}
+
+impl Offer {
+	fn into_item(self, value: StructuredValue, trace: &mut NodeTrace) -> Result<FoundItem> {
+		let found = match value {
+			StructuredValue::Price => self.price.map(|price| match self.currency.as_deref() {
+				Some(currency) => format!("{} {}", price, currency),
+				None => price
+			}),
+
+			StructuredValue::Availability => self.availability.map(|v| {
+				v.rsplit('/').next().unwrap_or_default().to_string()
+			})
+		};
+
+		let source = self.source;
+
+		let found = trace.record("value", &format!("{}:offers.{}", source, value.as_str()), found);
+		let title = trace.record("title", &format!("{}:name", source), self.name);
+		let link = trace.record("link", &format!("{}:url", source), self.url);
+		let unique_id = trace.record("unique_id", &format!("{}:sku", source), self.unique_id);
+
+		Ok(FoundItem {
+			value: found.ok_or_else(|| Error::MissingField("value".into()))?,
+			unique_id,
+			title,
+			link
+		})
+	}
+}
+
+
+/// Items for every Offer on the page and a trace for each of them.
+pub fn find_items(body: &str, value: StructuredValue) -> (Vec<FoundItem>, Vec<NodeTrace>) {
+	let html = Html::parse_document(body);
+
+	let mut offers = find_json_ld_offers(&html);
+
+	if offers.is_empty() {
+		offers = find_microdata_offers(&html);
+	}
+
+	let mut items = Vec::new();
+	let mut nodes = Vec::new();
+
+	for (index, offer) in offers.into_iter().enumerate() {
+		let mut trace = NodeTrace::new(index);
+
+		match offer.into_item(value, &mut trace) {
+			Ok(item) => items.push(item),
+			Err(e) => trace.fail(&e)
+		}
+
+		nodes.push(trace);
+	}
+
+	(items, nodes)
+}
+
+
+// JSON-LD
+
+const UNIQUE_ID_KEYS: [&str; 7] = ["sku", "gtin13", "gtin", "gtin12", "gtin14", "gtin8", "productID"];
+
+fn find_json_ld_offers(html: &Html) -> Vec<Offer> {
+	let selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+
+	let mut products = Vec::new();
+
+	for script in html.select(&selector) {
+		match serde_json::from_str::<Value>(&script.text().collect::<String>()) {
+			Ok(value) => collect_products(value, &mut products),
+			Err(e) => log::debug!("Invalid JSON-LD: {}", e)
+		}
+	}
+
+	let mut offers = Vec::new();
+
+	for product in products {
+		let offer_values = match product.get("offers") {
+			Some(Value::Array(v)) => v.iter().filter_map(|v| v.as_object()).collect(),
+			Some(Value::Object(v)) => vec![v],
+			_ => Vec::new()
+		};
+
+		let product_id = json_first(&product, &UNIQUE_ID_KEYS);
+
+		// Still counted so a Product without offers shows up in the trace.
+		if offer_values.is_empty() {
+			offers.push(Offer {
+				source: "json-ld",
+				name: json_string(&product, "name"),
+				url: json_string(&product, "url"),
+				unique_id: product_id.clone(),
+				price: None,
+				currency: None,
+				availability: None
+			});
+		}
+
+		for offer in offer_values {
+			let price_spec = offer.get("priceSpecification").and_then(|v| v.as_object());
+
+			offers.push(Offer {
+				source: "json-ld",
+				name: json_string(&product, "name"),
+				url: json_string(offer, "url").or_else(|| json_string(&product, "url")),
+				unique_id: json_first(offer, &UNIQUE_ID_KEYS).or_else(|| product_id.clone()),
+				price: json_first(offer, &["price", "lowPrice"])
+					.or_else(|| price_spec.and_then(|v| json_string(v, "price"))),
+				currency: json_string(offer, "priceCurrency")
+					.or_else(|| price_spec.and_then(|v| json_string(v, "priceCurrency"))),
+				availability: json_string(offer, "availability")
+			});
+		}
+	}
+
+	offers
+}
+
+/// Products anywhere in the value. Includes ones in `@graph` or nested in other types.
+fn collect_products(value: Value, products: &mut Vec<Map<String, Value>>) {
+	match value {
+		Value::Array(values) => values.into_iter().for_each(|v| collect_products(v, products)),
+
+		Value::Object(object) => {
+			if is_type(object.get("@type"), "Product") {
+				products.push(object);
+			} else {
+				object.into_iter().for_each(|(_, v)| collect_products(v, products));
+			}
+		}
+
+		_ => ()
+	}
+}
+
+fn is_type(value: Option<&Value>, name: &str) -> bool {
+	match value {
+		Some(Value::String(v)) => v == name || v.ends_with(&format!("/{}", name)),
+		Some(Value::Array(v)) => v.iter().any(|v| is_type(Some(v), name)),
+		_ => false
+	}
+}
+
+fn json_string(object: &Map<String, Value>, key: &str) -> Option<String> {
+	match object.get(key)? {
+		Value::String(v) => Some(v.trim().to_string()).filter(|v| !v.is_empty()),
+		Value::Number(v) => Some(v.to_string()),
+		_ => None
+	}
+}
+
+fn json_first(object: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+	keys.iter().find_map(|key| json_string(object, key))
+}
+
+
+// Microdata
+
+fn find_microdata_offers(html: &Html) -> Vec<Offer> {
+	let selector = Selector::parse("[itemscope][itemtype]").unwrap();
+
+	let mut offers = Vec::new();
+
+	let is_product = |el: &ElementRef<'_>| el.value().attr("itemtype")
+		.map(|v| v.split_whitespace().any(|v| v.ends_with("/Product")))
+		.unwrap_or_default();
+
+	for product in html.select(&selector).filter(is_product) {
+		let name = microdata_prop(product, "name");
+		let url = microdata_prop(product, "url");
+		let product_id = UNIQUE_ID_KEYS.iter().find_map(|key| microdata_prop(product, key));
+
+		let offer_scopes = microdata_scopes(product, "offers");
+
+		if offer_scopes.is_empty() {
+			offers.push(Offer {
+				source: "microdata",
+				name: name.clone(),
+				url: url.clone(),
+				unique_id: product_id.clone(),
+				price: None,
+				currency: None,
+				availability: None
+			});
+		}
+
+		for offer in offer_scopes {
+			offers.push(Offer {
+				source: "microdata",
+				name: name.clone(),
+				url: microdata_prop(offer, "url").or_else(|| url.clone()),
+				unique_id: UNIQUE_ID_KEYS.iter().find_map(|key| microdata_prop(offer, key)).or_else(|| product_id.clone()),
+				price: microdata_prop(offer, "price").or_else(|| microdata_prop(offer, "lowPrice")),
+				currency: microdata_prop(offer, "priceCurrency"),
+				availability: microdata_prop(offer, "availability")
+			});
+		}
+	}
+
+	offers
+}
+
+/// Elements with the property which belong to the scope and not to a scope inside it.
+fn microdata_elements<'a>(scope: ElementRef<'a>, name: &str) -> impl Iterator<Item = ElementRef<'a>> + 'a {
+	let name = name.to_string();
+
+	scope.descendants()
+		.skip(1)
+		.filter_map(ElementRef::wrap)
+		.filter(move |el| el.value().attr("itemprop").map(|v| v.split_whitespace().any(|v| v == name)).unwrap_or_default())
+		.filter(move |el| {
+			el.ancestors()
+				.filter_map(ElementRef::wrap)
+				.find(|v| v.value().attr("itemscope").is_some())
+				.map(|v| v.id() == scope.id())
+				.unwrap_or_default()
+		})
+}
+
+fn microdata_scopes<'a>(scope: ElementRef<'a>, name: &str) -> Vec<ElementRef<'a>> {
+	microdata_elements(scope, name)
+		.filter(|v| v.value().attr("itemscope").is_some())
+		.collect()
+}
+
+fn microdata_prop(scope: ElementRef<'_>, name: &str) -> Option<String> {
+	let el = microdata_elements(scope, name).next()?;
+	let value = el.value();
+
+	let found = match value.name() {
+		_ if value.attr("content").is_some() => value.attr("content").map(|v| v.to_string()),
+		"a" | "link" | "area" => value.attr("href").map(|v| v.to_string()),
+		"img" | "audio" | "video" | "source" => value.attr("src").map(|v| v.to_string()),
+		"data" | "meter" => value.attr("value").map(|v| v.to_string()),
+		"time" => value.attr("datetime").map(|v| v.to_string()),
+		_ => Some(el.text().collect::<String>())
+	};
+
+	found.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
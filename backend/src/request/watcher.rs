@@ -1,4 +1,4 @@
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use reqwest::Client;
 use serde::{Serialize, Deserialize};
 use url::Url;
@@ -8,14 +8,21 @@ use diesel::SqliteConnection;
 
 use crate::feature::schema::{watching as WatchingSchema};
 use crate::feature::models::{EditWatchParserItemModel, NewWatchHistoryModel, NewWatchParserItemModel, NewWatchingModel, QueryId, WatchingModel};
-use crate::{Result, Error, xpath};
+use crate::{Result, Error, http, metrics};
+use crate::xpath::NodeTrace;
+use crate::selector::{self, ItemNode, Page, SelectorLanguage};
+use crate::links::LinkResolver;
+use super::structured::{self, StructuredValue};
+use super::pagination::{Pages, Pagination};
+use super::page_text::{self, PageText};
 use super::feeds::custom::ParseOpts;
-use super::{RequestResults, ItemResults, RequestItemResults, InnerRequestResults};
+use super::{RequestResults, ItemResults, RequestItemResults, InnerRequestResults, ParserStats, BASELINE_RUNS};
 
 use crate::feature::objects::{
-	get_watch_parser_from_url, get_watch_parser_by_id,
+	get_watch_parser_from_url, get_watch_parsers_from_url, get_watch_parser_by_id,
 	get_watchers,
-	get_last_watch_history, create_last_watch_history
+	get_last_watch_history, create_last_watch_history,
+	get_parser_runs
 };
 
 
@@ -31,7 +38,11 @@ pub struct WatchParserItem {
 	pub description: String,
 	pub match_url: String,
 
-	pub match_opts: MatchParser
+	pub match_opts: MatchParser,
+
+	/// Higher is picked first when more than one `match_url` matches.
+	#[serde(default)]
+	pub priority: i32
 }
 
 impl From<WatchParserItem> for NewWatchParserItemModel {
@@ -40,7 +51,8 @@ impl From<WatchParserItem> for NewWatchParserItemModel {
 			title: val.title,
 			description: val.description,
 			match_url: val.match_url,
-			match_opts: serde_json::to_string(&val.match_opts).unwrap()
+			match_opts: serde_json::to_string(&val.match_opts).unwrap(),
+			priority: val.priority
 		}
 	}
 }
@@ -51,7 +63,8 @@ pub struct UpdateableWatchParser {
 	pub description: Option<String>,
 	pub match_url: Option<String>,
 
-	pub match_opts: Option<MatchParser>
+	pub match_opts: Option<MatchParser>,
+	pub priority: Option<i32>
 }
 
 
@@ -61,7 +74,8 @@ impl From<UpdateableWatchParser> for EditWatchParserItemModel {
 			title: val.title,
 			description: val.description,
 			match_url: val.match_url,
-			match_opts: val.match_opts.as_ref().map(serde_json::to_string).and_then(|v| v.ok())
+			match_opts: val.match_opts.as_ref().map(serde_json::to_string).and_then(|v| v.ok()),
+			priority: val.priority
 		}
 	}
 }
@@ -71,6 +85,8 @@ impl From<UpdateableWatchParser> for EditWatchParserItemModel {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MatchParser {
 	pub items: String,
+	#[serde(default)]
+	pub items_language: SelectorLanguage,
 
 	/// Value is used to check for changes.
 	pub value: ParseOpts,
@@ -80,8 +96,66 @@ pub struct MatchParser {
 
 	pub title: Option<ParseOpts>,
 	pub link: Option<ParseOpts>,
+
+	/// Removes tracking query parameters (utm_*, fbclid, ..) from resolved links.
+	#[serde(default)]
+	pub strip_tracking: bool,
+
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub pagination: Option<Pagination>
 }
 
+impl MatchParser {
+	/// Checks every selector and parse option so a broken parser isn't saved.
+	pub fn validate(&self) -> Result<()> {
+		selector::validate("items", &self.items, self.items_language, self.items_language)?;
+
+		if let Some(pagination) = self.pagination.as_ref() {
+			pagination.validate(self.items_language)?;
+		}
+
+		let fields = [
+			("value", Some(&self.value)),
+			("unique_id", self.unique_id.as_ref()),
+			("title", self.title.as_ref()),
+			("link", self.link.as_ref())
+		];
+
+		for (name, opts) in fields {
+			if let Some(opts) = opts {
+				for value in opts.selectors() {
+					selector::validate(name, value, opts.language, self.items_language)?;
+				}
+
+				opts.validate_steps(name)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+
+
+/// A watch parser which matched a URL and the `match_url` pattern it matched through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParserMatch {
+	pub pattern: String,
+	pub parser: WatchParserItem
+}
+
+/// Watch parsers matching the URL, using the same lookup as `verify_new_watcher`. The first one is used.
+pub fn find_parsers_for_url(url: &str, conn: &SqliteConnection) -> Result<Vec<ParserMatch>> {
+	Ok(
+		get_watch_parsers_from_url(&Url::parse(url)?, conn)?
+			.into_iter()
+			.map(|parser| ParserMatch {
+				pattern: parser.match_url.clone(),
+				parser
+			})
+			.collect()
+	)
+}
 
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -92,6 +166,75 @@ pub struct FoundItem {
 	pub link: Option<String>,
 }
 
+impl FoundItem {
+	/// Used to pair up the same item across two history snapshots.
+	pub fn key(&self) -> Option<&str> {
+		self.unique_id.as_deref()
+			.or(self.link.as_deref())
+			.or(self.title.as_deref())
+	}
+}
+
+
+/// A single item which differs between two history snapshots.
+/// `old_value` is None if the item is new, `new_value` is None if it was removed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FoundItemChange {
+	pub old_value: Option<String>,
+	pub new_value: Option<String>,
+
+	pub unique_id: Option<String>,
+	pub title: Option<String>,
+	pub link: Option<String>,
+}
+
+impl FoundItemChange {
+	fn new(item: &FoundItem, old_value: Option<String>, new_value: Option<String>) -> Self {
+		Self {
+			old_value,
+			new_value,
+
+			unique_id: item.unique_id.clone(),
+			title: item.title.clone(),
+			link: item.link.clone(),
+		}
+	}
+}
+
+
+pub fn get_item_changes(old_items: &[FoundItem], new_items: &[FoundItem]) -> Vec<FoundItemChange> {
+	let mut changes = Vec::new();
+	let mut matched_old = vec![false; old_items.len()];
+
+	for (index, new_item) in new_items.iter().enumerate() {
+		// Items without anything to identify them are compared by position.
+		let found = match new_item.key() {
+			Some(key) => old_items.iter().position(|i| i.key() == Some(key)),
+			None => Some(index).filter(|i| old_items.get(*i).map(|v| v.key().is_none()).unwrap_or_default())
+		};
+
+		match found {
+			Some(old_index) => {
+				matched_old[old_index] = true;
+
+				let old_item = &old_items[old_index];
+
+				if old_item.value != new_item.value {
+					changes.push(FoundItemChange::new(new_item, Some(old_item.value.clone()), Some(new_item.value.clone())));
+				}
+			}
+
+			None => changes.push(FoundItemChange::new(new_item, None, Some(new_item.value.clone())))
+		}
+	}
+
+	for (old_item, _) in old_items.iter().zip(matched_old).filter(|(_, matched)| !matched) {
+		changes.push(FoundItemChange::new(old_item, Some(old_item.value.clone()), None));
+	}
+
+	changes
+}
+
 
 pub struct RequestManager {
 	pub is_idle: bool,
@@ -106,28 +249,44 @@ impl RequestManager {
 		}
 	}
 
-	pub fn verify_new_watcher(&self, url: String, parser_id: Option<QueryId>, conn: &SqliteConnection) -> Result<NewWatchingModel> {
-		let item = if let Some(id) = parser_id {
-			get_watch_parser_by_id(id, conn)?
-		} else {
-			get_watch_parser_from_url(Url::parse(&url).unwrap(), conn)?
+	pub fn verify_new_watcher(&self, url: String, parser_id: Option<QueryId>, structured_data: Option<StructuredValue>, page_text: Option<&PageText>, conn: &SqliteConnection) -> Result<NewWatchingModel> {
+		let item = match page_text {
+			Some(opts) => {
+				if parser_id.is_some() || structured_data.is_some() {
+					return Err(Error::InvalidField("page_text".into(), "Can't be used with a parser or structured data.".into()));
+				}
+
+				opts.validate()?;
+
+				None
+			}
+
+			None => find_parser(&url, parser_id, structured_data, conn)?
+		};
+
+		let (parser_id, title, description) = match item {
+			Some(item) => (item.id, item.title, item.description),
+			None => (None, Url::parse(&url)?.host_str().unwrap_or_default().to_string(), String::new())
 		};
 
 		let watcher = NewWatchingModel {
-			parser_id: item.id,
+			parser_id,
 
 			enabled: true,
 
 			url,
 
-			title: item.title,
-			description: item.description,
+			title,
+			description,
 
 			sec_interval: 60 * 10,
 			remove_after: 0,
 
 			date_added: chrono::Utc::now().naive_utc().timestamp(),
 			last_called: chrono::Utc::now().naive_utc().timestamp(),
+
+			structured_data: structured_data.map(|v| v.as_str().to_string()),
+			page_text: page_text.map(serde_json::to_string).transpose()?
 		};
 
 		Ok(watcher)
@@ -178,8 +337,15 @@ impl RequestManager {
 		for feed in &feeds {
 			let feed_cloned = (*feed).clone();
 
+			metrics::record_scheduler_lag("watcher", chrono::Utc::now().timestamp() - feed.last_called - feed.sec_interval as i64);
+
+			let started = Instant::now();
+			let feed_results = request_feed(&feed_cloned, req_client, connection).await;
+
+			metrics::record_fetch("watcher", feed.id, started.elapsed(), feed_results.as_ref().err());
+
 			results.items.push(ItemResults {
-				results: request_feed(&feed_cloned, req_client, connection).await,
+				results: feed_results,
 				item: feed_cloned
 			});
 		}
@@ -200,6 +366,8 @@ impl RequestManager {
 
 						if let Ok(count) = count {
 							res.new_item_count = count;
+
+							metrics::record_watcher_change(item.watch_id);
 						}
 					}
 				}
@@ -215,53 +383,151 @@ impl RequestManager {
 }
 
 
-pub async fn get_from_url_parser(req_client: &Client, url: &str, parser: &MatchParser) -> Result<Vec<FoundItem>> {
-	let resp = req_client.get(url).send().await?.text().await?;
-	let mut reader = std::io::Cursor::new(resp);
-
-	let doc = xpather::parse_document(&mut reader)?;
-
-	Ok(doc.evaluate(&parser.items)?
-		.collect_nodes()?
-		.into_iter()
-		.map::<Result<FoundItem>, _>(|node| {
-			// Find value.
-			let value = parser.value.evaluate(&doc, &node)?
-				.next()
-				.transpose()?
-				.map(|v| Result::Ok(parser.value.parse(&v.convert_to_string()?)?))
-				.transpose()?
-				.map(|v| v.trim().escape_default().to_string())
-				.ok_or_else(|| Error::Other("Missing Required Title.".into()))?;
-
-			// Find title.
-			let title = xpath::get_optional_string(parser.title.as_ref(), &doc, &node)?
-				.map(|v| v.trim().escape_default().to_string());
-
-			// Find link.
-			let link = xpath::get_optional_string(parser.link.as_ref(), &doc, &node)?
-				.map(|v| v.trim().escape_default().to_string());
-
-			// Unique ID
-			let unique_id = xpath::get_optional_string(parser.unique_id.as_ref(), &doc, &node)?
-				.map(|v| v.trim().escape_default().to_string());
-
-			Ok(FoundItem {
-				value,
-				unique_id,
-				title,
-				link
-			})
-		})
-		.filter_map(|i| {
-			if i.is_err() {
-				log::error!("EVALUATION ERROR: {:?}", i);
-			}
+pub async fn get_from_url(req_client: &Client, url: &str, structured_data: Option<StructuredValue>, page_text: Option<&PageText>, parser: Option<&MatchParser>) -> Result<Vec<FoundItem>> {
+	let (items, nodes, ..) = get_items(req_client, url, structured_data, page_text, parser).await?;
 
-			i.ok()
-		})
-		.collect()
-	)
+	for node in nodes.iter().filter(|v| v.error.is_some()) {
+		log::error!("EVALUATION ERROR: {:?}", node);
+	}
+
+	Ok(items)
+}
+
+/// Items which were found, a trace of every node `items` matched and the pages fetched.
+pub async fn test_url_parser(req_client: &Client, url: &str, parser: &MatchParser) -> Result<(Vec<FoundItem>, Vec<NodeTrace>, usize)> {
+	let first = http::get_body(req_client, url).await?;
+
+	parse_pages(req_client, url, first, parser).await
+}
+
+/// Items from the page text, structured data and/or the parser depending on what the watcher uses.
+/// The bool is true if they came from structured data. Also returns the pages fetched.
+pub async fn get_items(req_client: &Client, url: &str, structured_data: Option<StructuredValue>, page_text: Option<&PageText>, parser: Option<&MatchParser>) -> Result<(Vec<FoundItem>, Vec<NodeTrace>, bool, usize)> {
+	if let Some(opts) = page_text {
+		let (_, resp) = http::get_body(req_client, url).await?;
+
+		let (items, nodes) = page_text::find_items(&resp, opts);
+
+		return Ok((items, nodes, false, 1));
+	}
+
+	match (structured_data, parser) {
+		(Some(value), parser) => test_url_structured(req_client, url, value, parser).await,
+
+		(None, Some(parser)) => {
+			let (items, nodes, pages) = test_url_parser(req_client, url, parser).await?;
+
+			Ok((items, nodes, false, pages))
+		}
+
+		(None, None) => Err(Error::ParserNotFound(url.to_string()))
+	}
+}
+
+/// Items from the page's schema.org Product data if there's any, otherwise from the parser.
+/// The bool is true if they came from structured data. Also returns the pages fetched.
+pub async fn test_url_structured(req_client: &Client, url: &str, structured_data: StructuredValue, parser: Option<&MatchParser>) -> Result<(Vec<FoundItem>, Vec<NodeTrace>, bool, usize)> {
+	let (final_url, resp) = http::get_body(req_client, url).await?;
+
+	let links = LinkResolver::new(final_url.clone(), &resp, parser.map(|v| v.strip_tracking).unwrap_or_default());
+
+	let (mut items, nodes) = structured::find_items(&resp, structured_data);
+
+	for item in &mut items {
+		item.link = item.link.as_deref().map(|v| links.resolve(v));
+	}
+
+	match parser {
+		Some(parser) if items.is_empty() => {
+			let (items, nodes, pages) = parse_pages(req_client, url, (final_url, resp), parser).await?;
+
+			Ok((items, nodes, false, pages))
+		}
+
+		_ => Ok((items, nodes, true, 1))
+	}
+}
+
+/// The parser set on the watcher or the one matching its URL.
+/// With structured data the parser is only a fallback so it's fine if there's none.
+pub fn find_parser(url: &str, parser_id: Option<QueryId>, structured_data: Option<StructuredValue>, conn: &SqliteConnection) -> Result<Option<WatchParserItem>> {
+	let found = match parser_id {
+		Some(id) => get_watch_parser_by_id(id, conn).map_err(Error::from),
+		None => get_watch_parser_from_url(Url::parse(url)?, conn).map_err(|e| Error::from_parser_lookup(e, url))
+	};
+
+	match found {
+		Ok(v) => Ok(Some(v)),
+		Err(Error::ParserNotFound(_)) if structured_data.is_some() => Ok(None),
+		Err(e) => Err(e)
+	}
+}
+
+/// Runs the parser on the already fetched first page and every page after it.
+async fn parse_pages(req_client: &Client, url: &str, first: (Url, String), parser: &MatchParser) -> Result<(Vec<FoundItem>, Vec<NodeTrace>, usize)> {
+	let mut pages = Pages::new(parser.pagination.as_ref(), url);
+
+	let mut items = Vec::new();
+	let mut nodes = Vec::new();
+
+	let (mut final_url, mut resp) = first;
+
+	while let Some(next) = parse_body(final_url, &resp, parser, &mut pages, &mut items, &mut nodes)? {
+		pages.wait().await;
+
+		(final_url, resp) = http::get_body(req_client, &next).await?;
+	}
+
+	Ok((items, nodes, pages.count()))
+}
+
+/// Adds the items on the page. Returns the next page's URL.
+fn parse_body(final_url: Url, body: &str, parser: &MatchParser, pages: &mut Pages<'_>, items: &mut Vec<FoundItem>, nodes: &mut Vec<NodeTrace>) -> Result<Option<String>> {
+	let links = LinkResolver::new(final_url, body, parser.strip_tracking);
+	let page = Page::parse(body, parser.items_language)?;
+
+	let found = page.select_items(&parser.items)?;
+	let node_count = found.len();
+
+	for node in found {
+		let mut trace = NodeTrace::new(nodes.len());
+		trace.page = pages.count();
+
+		match evaluate_node(parser, &page, &node, &links, &mut trace) {
+			Ok(item) => items.push(item),
+			Err(e) => trace.fail(&e)
+		}
+
+		nodes.push(trace);
+	}
+
+	pages.next_url(&page, &links, node_count)
+}
+
+fn evaluate_node(parser: &MatchParser, page: &Page, node: &ItemNode<'_>, links: &LinkResolver, trace: &mut NodeTrace) -> Result<FoundItem> {
+	// Find value.
+	let value = trace.get_optional_string("value", Some(&parser.value), page, node)?
+		.map(|v| v.trim().escape_default().to_string())
+		.ok_or_else(|| Error::MissingField("value".into()))?;
+
+	// Find title.
+	let title = trace.get_optional_string("title", parser.title.as_ref(), page, node)?
+		.map(|v| v.trim().escape_default().to_string());
+
+	// Find link.
+	let link = trace.get_optional_string("link", parser.link.as_ref(), page, node)?
+		.map(|v| links.resolve(&v).escape_default().to_string());
+
+	// Unique ID
+	let unique_id = trace.get_optional_string("unique_id", parser.unique_id.as_ref(), page, node)?
+		.map(|v| v.trim().escape_default().to_string());
+
+	Ok(FoundItem {
+		value,
+		unique_id,
+		title,
+		link
+	})
 }
 
 
@@ -273,25 +539,46 @@ pub async fn request_feed(feed: &WatchingModel, req_client: &Client, conn: &Sqli
 		duration: Duration::new(0, 0),
 		new_item_count: 0,
 		item_count: 0,
-		to_insert: Vec::new()
+		to_insert: Vec::new(),
+		parser_stats: None
 	};
 
-	let parser = if let Some(parser_id) = feed.parser_id {
-		get_watch_parser_by_id(parser_id, conn)?
-	} else {
-		get_watch_parser_from_url(Url::parse(&feed.url).unwrap(), conn)?
+	let structured_data = feed.structured_data.as_deref().map(str::parse::<StructuredValue>).transpose()?;
+	let page_text = feed.page_text.as_deref().map(serde_json::from_str::<PageText>).transpose()?;
+
+	let parser = match page_text {
+		Some(_) => None,
+		None => find_parser(&feed.url, feed.parser_id, structured_data, conn)?
 	};
 
-	let new_items = get_from_url_parser(req_client, &feed.url, &parser.match_opts).await?;
+	let (new_items, nodes, _, pages) = get_items(req_client, &feed.url, structured_data, page_text.as_ref(), parser.as_ref().map(|v| &v.match_opts)).await?;
+
+	let mut stats = ParserStats::new(&nodes, pages);
+	stats.check_degraded(&get_parser_runs(None, Some(feed.id), BASELINE_RUNS, conn)?);
+
+	if let Some(reason) = stats.degraded.as_ref() {
+		log::warn!(r#"Watcher "{}" parser is degraded: {}"#, feed.title, reason);
+	} else if let Some(reason) = stats.fallback_reason() {
+		log::warn!(r#"Watcher "{}" parser primary selector failed: {}"#, feed.title, reason);
+	}
+
+	feed_res.parser_stats = Some(stats);
 
 	if let Some(last_item) = get_last_watch_history(feed.id, conn)? {
 		// Anything in the new_items is not in the last_items?
 		if new_items.iter().any(|v| !last_item.items.contains(v)) {
+			let diff = page_text.as_ref().map(|_| page_text::diff(
+				last_item.items.first().map(|v| v.value.as_str()).unwrap_or_default(),
+				new_items.first().map(|v| v.value.as_str()).unwrap_or_default()
+			));
+
 			feed_res.to_insert.push(NewWatchHistoryModel {
 				watch_id: feed.id,
 				items: serde_json::to_string(&new_items).unwrap(),
 
-				date_added: chrono::Utc::now().timestamp()
+				date_added: chrono::Utc::now().timestamp(),
+
+				diff
 			});
 		}
 	} else {
@@ -300,7 +587,9 @@ pub async fn request_feed(feed: &WatchingModel, req_client: &Client, conn: &Sqli
 			watch_id: feed.id,
 			items: serde_json::to_string(&new_items).unwrap(),
 
-			date_added: chrono::Utc::now().timestamp()
+			date_added: chrono::Utc::now().timestamp(),
+
+			diff: None
 		}, conn)?;
 	}
 
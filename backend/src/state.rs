@@ -22,6 +22,7 @@ use crate::request::{
 	watcher::RequestManager as WatcherRequestManager
 };
 use crate::config::ConfigManager;
+use crate::health;
 
 
 pub struct CoreState {
@@ -61,7 +62,19 @@ impl CoreState {
 			let mut write = self.config.write().unwrap();
 
 			write.init();
-			write.load().unwrap_or_else(|e| panic!("Loading Config Error: {}", e));
+
+			if let Err(e) = write.load() {
+				health::set_config_status(Err(e.to_string()));
+				panic!("Loading Config Error: {}", e);
+			}
+
+			health::set_config_status(Ok(()));
+
+			let config = write.config();
+
+			crate::metrics::configure(&config.metrics);
+			health::configure(&config.health);
+			crate::backup::schedule(&config);
 		}
 
 		#[cfg(feature = "website")]
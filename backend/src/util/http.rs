@@ -0,0 +1,29 @@
+use reqwest::{Client, Response};
+use url::Url;
+
+use crate::Result;
+use crate::metrics;
+
+
+/// GET request which also records the response status in the metrics.
+pub async fn get(req_client: &Client, url: &str) -> Result<Response> {
+	match req_client.get(url).send().await {
+		Ok(resp) => {
+			metrics::record_http_status(Some(resp.status().as_u16()));
+			Ok(resp)
+		}
+
+		Err(e) => {
+			metrics::record_http_status(e.status().map(|s| s.as_u16()));
+			Err(e.into())
+		}
+	}
+}
+
+/// Body of a GET request and the URL it ended up at after redirects.
+pub async fn get_body(req_client: &Client, url: &str) -> Result<(Url, String)> {
+	let resp = get(req_client, url).await?;
+	let final_url = resp.url().clone();
+
+	Ok((final_url, resp.text().await?))
+}
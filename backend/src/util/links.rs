@@ -0,0 +1,84 @@
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use url::Url;
+
+
+/// Query parameters removed when a parser has `strip_tracking` set. Names ending in `_` are prefixes.
+const TRACKING_PARAMS: [&str; 12] = [
+	"utm_", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid",
+	"yclid", "igshid", "mc_cid", "mc_eid", "_hsenc"
+];
+
+
+lazy_static! {
+	static ref BASE_HREF: Regex = Regex::new(r#"(?i)<base\s[^>]*?href\s*=\s*["']([^"']+)["']"#).unwrap();
+	static ref HTML_URL_ATTR: Regex = Regex::new(r#"(?i)(\s(?:href|src|poster)\s*=\s*)(["'])([^"']*)(["'])"#).unwrap();
+}
+
+
+/// Resolves links found on a page. Relative to the page's `<base href>` if it has one.
+#[derive(Debug, Clone)]
+pub struct LinkResolver {
+	base: Url,
+	strip_tracking: bool
+}
+
+impl LinkResolver {
+	/// `response_url` is where the request ended up after redirects.
+	pub fn new(response_url: Url, body: &str, strip_tracking: bool) -> Self {
+		let base = BASE_HREF.captures(body)
+			.and_then(|v| response_url.join(v[1].trim()).ok())
+			.unwrap_or(response_url);
+
+		Self {
+			base,
+			strip_tracking
+		}
+	}
+
+	/// Absolute URL for the link. Returned unchanged if it can't be joined.
+	pub fn resolve(&self, link: &str) -> String {
+		let link = link.trim();
+
+		let mut url = match self.base.join(link) {
+			Ok(v) => v,
+			Err(_) => return link.to_string()
+		};
+
+		if self.strip_tracking && url.query().is_some() {
+			let kept = url.query_pairs()
+				.filter(|(name, _)| !is_tracking_param(name))
+				.map(|(name, value)| (name.into_owned(), value.into_owned()))
+				.collect::<Vec<_>>();
+
+			if kept.is_empty() {
+				url.set_query(None);
+			} else {
+				url.query_pairs_mut().clear().extend_pairs(kept);
+			}
+		}
+
+		url.to_string()
+	}
+
+	/// Resolves `href`, `src` and `poster` attributes in an HTML fragment.
+	pub fn resolve_html(&self, html: &str) -> String {
+		HTML_URL_ATTR.replace_all(html, |caps: &Captures<'_>| {
+			// Fragments and scripts point at the page itself.
+			if caps[3].starts_with('#') || caps[3].trim_start().to_lowercase().starts_with("javascript:") || caps[2] != caps[4] {
+				return caps[0].to_string();
+			}
+
+			let resolved = self.resolve(&caps[3].replace("&amp;", "&")).replace('&', "&amp;");
+
+			format!("{}{}{}{}", &caps[1], &caps[2], resolved, &caps[4])
+		}).into_owned()
+	}
+}
+
+
+fn is_tracking_param(name: &str) -> bool {
+	let name = name.to_lowercase();
+
+	TRACKING_PARAMS.iter().any(|v| if v.ends_with('_') { name.starts_with(v) } else { name == *v })
+}
@@ -1,2 +1,5 @@
 pub mod http;
-pub mod xpath;
\ No newline at end of file
+pub mod xpath;
+pub mod pattern;
+pub mod selector;
+pub mod links;
\ No newline at end of file
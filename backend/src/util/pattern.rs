@@ -0,0 +1,127 @@
+use std::cmp::Reverse;
+
+use regex::{Regex, RegexBuilder};
+use url::Url;
+
+use crate::{Error, Result};
+
+
+const REGEX_PREFIX: &str = "regex:";
+
+
+/// A parser's `match_url`.
+///
+/// - `example.com` or `*.example.com` only match the host. `*.example.com` also matches `example.com`.
+/// - `example.com/product/*` matches host and path. Add a `?` to also match the query (`example.com/search?q=*`).
+/// - `regex:^example\.com/p/\d+` is matched against `host/path` with `?query` if the URL has one.
+#[derive(Debug, Clone)]
+pub struct UrlPattern {
+	regex: Regex,
+	target: Target,
+	/// Characters which aren't wildcards. Used to pick the most specific pattern.
+	literal_len: usize,
+	has_wildcard: bool
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+	Host,
+	Path,
+	Query,
+	Regex
+}
+
+impl UrlPattern {
+	pub fn parse(value: &str) -> Result<Self> {
+		let value = value.trim();
+
+		if value.is_empty() {
+			return Err(Error::InvalidField("match_url".into(), "Pattern is empty.".into()));
+		}
+
+		if let Some(expr) = value.strip_prefix(REGEX_PREFIX) {
+			let regex = RegexBuilder::new(expr)
+				.case_insensitive(true)
+				.build()
+				.map_err(|e| Error::InvalidField("match_url".into(), format!("Invalid regex: {}", e)))?;
+
+			return Ok(Self {
+				regex,
+				target: Target::Regex,
+				literal_len: 0,
+				has_wildcard: true
+			});
+		}
+
+		let target = if value.contains('?') {
+			Target::Query
+		} else if value.contains('/') {
+			Target::Path
+		} else {
+			Target::Host
+		};
+
+		let (prefix, rest) = match value.strip_prefix("*.") {
+			Some(rest) if target == Target::Host || !rest.starts_with('/') => ("(?:.*\\.)?", rest),
+			_ => ("", value)
+		};
+
+		let expr = rest.split('*').map(regex::escape).collect::<Vec<_>>().join(".*");
+
+		let regex = RegexBuilder::new(&format!("^{}{}$", prefix, expr))
+			.case_insensitive(true)
+			.build()?;
+
+		Ok(Self {
+			regex,
+			target,
+			literal_len: rest.chars().filter(|v| *v != '*').count(),
+			has_wildcard: value.contains('*')
+		})
+	}
+
+	pub fn is_match(&self, url: &Url) -> bool {
+		let host = match url.host_str() {
+			Some(v) => v,
+			None => return false
+		};
+
+		match self.target {
+			Target::Host => self.regex.is_match(host),
+			Target::Path => self.regex.is_match(&format!("{}{}", host, url.path())),
+			Target::Query | Target::Regex => match url.query() {
+				Some(query) => self.regex.is_match(&format!("{}{}?{}", host, url.path(), query)),
+				None => self.regex.is_match(&format!("{}{}", host, url.path()))
+			}
+		}
+	}
+
+	/// Higher is more specific. Regex patterns are always the least specific.
+	pub fn specificity(&self) -> (bool, usize, bool) {
+		(self.target != Target::Regex, self.literal_len, !self.has_wildcard)
+	}
+}
+
+
+/// Items whose pattern matches the URL. Sorted by priority, then most specific pattern.
+pub fn find_matches<T, F: Fn(&T) -> (&str, i32)>(url: &Url, items: Vec<T>, get_pattern: F) -> Vec<T> {
+	let mut found = items.into_iter()
+		.filter_map(|item| {
+			let (value, priority) = get_pattern(&item);
+
+			match UrlPattern::parse(value) {
+				Ok(pattern) if pattern.is_match(url) => Some((priority, pattern.specificity(), item)),
+				Ok(_) => None,
+				Err(e) => {
+					log::error!("Invalid match_url {:?}: {}", value, e);
+					None
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// Stable. Equal ones keep their insert order.
+	found.sort_by_key(|v| Reverse((v.0, v.1)));
+
+	found.into_iter().map(|v| v.2).collect()
+}
@@ -0,0 +1,277 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use xpather::Document;
+use xpather::value::Node;
+
+use crate::{Error, Result};
+use crate::request::feeds::custom::{Parse, ParseOpts};
+
+
+/// Syntax of a parser selector. Stored parsers from before this are XPath.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectorLanguage {
+	#[default]
+	Xpath,
+	/// A CSS selector, optionally ending with `::text` (default) or `::attr(name)`.
+	/// Without a selector (ex. `::attr(href)`) it's the item node itself.
+	Css,
+	/// For JSON responses. Fields are relative to the item, `$` being the item itself.
+	JsonPath
+}
+
+
+/// A fetched page or JSON response. Only parsed for the language the parser's `items` uses.
+pub enum Page {
+	Xpath(Document),
+	Css(Html),
+	Json(Value)
+}
+
+/// A node matched by a parser's `items`.
+pub enum ItemNode<'a> {
+	Xpath(Node),
+	Css(ElementRef<'a>),
+	Json(&'a Value)
+}
+
+impl Page {
+	pub fn parse(body: &str, language: SelectorLanguage) -> Result<Self> {
+		Ok(match language {
+			SelectorLanguage::Xpath => Page::Xpath(xpather::parse_document(&mut std::io::Cursor::new(body))?),
+			SelectorLanguage::Css => Page::Css(Html::parse_document(body)),
+			SelectorLanguage::JsonPath => Page::Json(serde_json::from_str(body)?)
+		})
+	}
+
+	pub fn select_items(&self, selector: &str) -> Result<Vec<ItemNode<'_>>> {
+		Ok(match self {
+			Page::Xpath(doc) => doc.evaluate(selector)?.collect_nodes()?.into_iter().map(ItemNode::Xpath).collect(),
+
+			Page::Css(html) => {
+				let css = CssSelector::parse(selector)?;
+
+				match css.selector.as_ref() {
+					Some(selector) => html.select(selector).map(ItemNode::Css).collect(),
+					None => return Err(Error::Other("CSS items selector is empty.".into()))
+				}
+			}
+
+			Page::Json(value) => select_json(value, selector)?.into_iter().map(ItemNode::Json).collect()
+		})
+	}
+
+	/// First value the selector gives from the whole page. ex. a next page link.
+	pub fn select_value(&self, selector: &str) -> Result<Option<String>> {
+		Ok(match self {
+			Page::Xpath(doc) => match doc.evaluate(selector)?.next().transpose()? {
+				Some(value) => match value.clone().convert_to_string() {
+					Ok(v) => Some(v),
+
+					// Links work without selecting their @href.
+					Err(_) if value.is_node() => doc.evaluate_from("@href", value.as_node()?)?
+						.next()
+						.transpose()?
+						.map(|v| v.convert_to_string())
+						.transpose()?,
+
+					Err(e) => return Err(e.into())
+				},
+
+				None => None
+			},
+
+			Page::Css(html) => {
+				let css = CssSelector::parse(selector)?;
+
+				let found = match css.selector.as_ref() {
+					Some(selector) => html.select(selector).next(),
+					None => return Err(Error::Other("CSS selector is empty.".into()))
+				};
+
+				found.and_then(|el| match &css.extract {
+					Extract::Attr(name) => el.value().attr(name).map(|v| v.to_string()),
+					Extract::Text => Some(el.text().collect())
+				})
+			}
+
+			Page::Json(value) => select_json(value, selector)?.first().and_then(|v| json_to_string(v))
+		})
+	}
+
+	/// HTML of the first element the selector matches.
+	pub fn select_html(&self, selector: &str) -> Result<Option<String>> {
+		Ok(match self {
+			Page::Xpath(doc) => match doc.evaluate(selector)?.next().transpose()? {
+				Some(value) => value.as_node()?.as_simple_html(),
+				None => None
+			},
+
+			Page::Css(html) => match CssSelector::parse(selector)?.selector.as_ref() {
+				Some(selector) => html.select(selector).next().map(|el| el.html()),
+				None => return Err(Error::Other("CSS selector is empty.".into()))
+			},
+
+			Page::Json(_) => return Err(Error::Other("JSON responses don't have HTML.".into()))
+		})
+	}
+
+	/// Values the field's selector selects from the node. Only the first unless a step joins them.
+	/// `as_html` returns the node's HTML instead of its text.
+	pub fn values(&self, node: &ItemNode<'_>, opts: &ParseOpts, selector: &str, as_html: bool) -> Result<Vec<String>> {
+		let limit = if opts.joins() { usize::MAX } else { 1 };
+		let extraction = opts.extraction();
+
+		let mut values = Vec::new();
+
+		match (self, node, opts.language) {
+			(Page::Xpath(doc), ItemNode::Xpath(node), SelectorLanguage::Xpath) => {
+				for value in opts.evaluate(selector, doc, node)?.take(limit) {
+					let value = value?;
+
+					let found = match extraction {
+						Some(Parse::Attr(name)) => doc.evaluate_from(format!("@{}", name), value.as_node()?)?
+							.next()
+							.transpose()?
+							.map(|v| v.convert_to_string())
+							.transpose()?,
+
+						// xpather can't convert elements. Text nodes under it are joined instead.
+						Some(_) if value.is_node() => Some(
+							doc.evaluate_from("descendant-or-self::text()", value.as_node()?)?
+								.map(|v| v.and_then(|v| v.convert_to_string()))
+								.collect::<xpather::Result<String>>()?
+						),
+
+						_ if as_html => value.as_node()?.as_simple_html(),
+						_ => Some(value.convert_to_string()?)
+					};
+
+					values.extend(found);
+				}
+			}
+
+			(_, ItemNode::Css(element), SelectorLanguage::Css) => {
+				let css = CssSelector::parse(selector)?;
+
+				let found: Vec<ElementRef<'_>> = match css.selector.as_ref() {
+					Some(selector) => element.select(selector).take(limit).collect(),
+					None => vec![*element]
+				};
+
+				for el in found {
+					let value = match (extraction, &css.extract) {
+						(Some(Parse::Attr(name)), _) | (None, Extract::Attr(name)) => el.value().attr(name).map(|v| v.to_string()),
+						(None, Extract::Text) if as_html => Some(el.html()),
+						_ => Some(el.text().collect())
+					};
+
+					values.extend(value);
+				}
+			}
+
+			(_, ItemNode::Json(item), SelectorLanguage::JsonPath) => {
+				for value in select_json(item, selector)?.into_iter().take(limit) {
+					let value = match extraction {
+						Some(Parse::Attr(name)) => value.get(name).and_then(json_to_string),
+						_ => json_to_string(value)
+					};
+
+					values.extend(value);
+				}
+			}
+
+			(_, _, language) => return Err(Error::Other(format!("Field uses {:?} but items uses the other selector language.", language)))
+		}
+
+		Ok(values)
+	}
+}
+
+
+/// Checks the selector parses. `items_language` is what the parser's `items` uses.
+pub fn validate(field: &str, selector: &str, language: SelectorLanguage, items_language: SelectorLanguage) -> Result<()> {
+	if selector.trim().is_empty() && (language != SelectorLanguage::Css || field == "items") {
+		return Err(Error::InvalidField(field.into(), "Selector is empty.".into()));
+	}
+
+	if language != items_language {
+		return Err(Error::InvalidField(field.into(), format!("Selector language {:?} doesn't match items ({:?}).", language, items_language)));
+	}
+
+	let result = match language {
+		SelectorLanguage::Xpath => {
+			// Evaluating against an empty document still parses the whole expression.
+			xpather::parse_document(&mut std::io::Cursor::new("<html></html>"))
+				.and_then(|doc| doc.evaluate(selector)?.collect::<xpather::Result<Vec<_>>>())
+				.map(|_| ())
+				.map_err(|e| format!("Invalid XPath: {}", e))
+		}
+
+		SelectorLanguage::Css => CssSelector::parse(selector).map(|_| ()).map_err(|e| e.message()),
+
+		SelectorLanguage::JsonPath => jsonpath_lib::Selector::default()
+			.str_path(selector)
+			.map(|_| ())
+			.map_err(|e| format!("Invalid JSONPath: {}", e))
+	};
+
+	result.map_err(|reason| Error::InvalidField(field.into(), reason))
+}
+
+
+enum Extract {
+	Text,
+	Attr(String)
+}
+
+struct CssSelector {
+	selector: Option<Selector>,
+	extract: Extract
+}
+
+impl CssSelector {
+	fn parse(value: &str) -> Result<Self> {
+		let value = value.trim();
+
+		let (selector, extract) = match value.rfind("::") {
+			Some(pos) => {
+				let extract = match &value[pos + 2..] {
+					"text" => Extract::Text,
+					v if v.starts_with("attr(") && v.ends_with(')') => Extract::Attr(v[5..v.len() - 1].trim().to_string()),
+					v => return Err(Error::Other(format!("Unknown CSS extractor \"::{}\". Expected ::text or ::attr(name).", v)))
+				};
+
+				(value[..pos].trim(), extract)
+			}
+
+			None => (value, Extract::Text)
+		};
+
+		let selector = if selector.is_empty() {
+			None
+		} else {
+			Some(Selector::parse(selector).map_err(|e| Error::Other(format!("Invalid CSS selector \"{}\": {:?}", selector, e.kind)))?)
+		};
+
+		Ok(Self {
+			selector,
+			extract
+		})
+	}
+}
+
+
+fn select_json<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>> {
+	jsonpath_lib::select(value, path).map_err(|e| Error::Other(format!("JSONPath \"{}\": {}", path, e)))
+}
+
+/// Objects and arrays are returned as JSON. Null is treated as missing.
+fn json_to_string(value: &Value) -> Option<String> {
+	match value {
+		Value::Null => None,
+		Value::String(v) => Some(v.clone()),
+		v => Some(v.to_string())
+	}
+}
@@ -1,21 +1,144 @@
-use xpather::Document;
-use xpather::value::Node;
-
-use crate::Result;
+use serde::{Serialize, Deserialize};
+use crate::{Error, Result};
 use crate::request::feeds::custom::ParseOpts;
+use crate::selector::{ItemNode, Page};
+
+
+/// What a field evaluated to on a single node.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FieldTrace {
+	pub field: String,
+	/// Selector which gave the value. The primary one if none did.
+	pub xpath: String,
+	/// Index in `fallbacks` if the primary selector didn't give a value.
+	#[serde(default)]
+	pub fallback: Option<usize>,
+
+	/// Selector result before the `Parse` steps. A JSON list if `Join` used more than one.
+	pub raw: Option<String>,
+	/// Value after `Parse`.
+	pub parsed: Option<String>,
+	pub error: Option<String>
+}
+
+/// Every field evaluated on a node matched by `items`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NodeTrace {
+	pub index: usize,
+	/// Page the node was on. 1 is the feed or watcher URL.
+	#[serde(default)]
+	pub page: usize,
+	pub fields: Vec<FieldTrace>,
+
+	/// Field which stopped the node from becoming an item.
+	pub failed_field: Option<String>,
+	pub error: Option<String>
+}
+
+impl NodeTrace {
+	pub fn new(index: usize) -> Self {
+		Self {
+			index,
+			page: 1,
+			..Self::default()
+		}
+	}
+
+	/// Evaluates the first selector result as a string and records it.
+	pub fn get_optional_string(&mut self, field: &str, opt: Option<&ParseOpts>, page: &Page, node: &ItemNode<'_>) -> Result<Option<String>> {
+		self.evaluate(field, opt, page, node, false)
+	}
+
+	/// Tries the selector then each fallback until one gives a non-empty value.
+	/// If none do the primary selector's result is used.
+	pub fn evaluate(&mut self, field: &str, opt: Option<&ParseOpts>, page: &Page, node: &ItemNode<'_>, as_html: bool) -> Result<Option<String>> {
+		let opt = match opt {
+			Some(v) => v,
+			None => return Ok(None)
+		};
+
+		let mut primary = None;
+
+		for (index, selector) in opt.selectors().enumerate() {
+			let (mut trace, result) = Self::parse(field, opt, selector, page.values(node, opt, selector, as_html));
+
+			if matches!(&result, Ok(Some(v)) if !v.trim().is_empty()) {
+				trace.fallback = index.checked_sub(1);
+				self.fields.push(trace);
+
+				return result;
+			}
+
+			primary.get_or_insert((trace, result));
+		}
+
+		match primary {
+			Some((trace, result)) => {
+				self.fields.push(trace);
+				result
+			}
+
+			None => Ok(None)
+		}
+	}
+
+	/// Records a value which didn't come from a selector. `source` is shown in place of the XPath.
+	pub fn record(&mut self, field: &str, source: &str, value: Option<String>) -> Option<String> {
+		self.fields.push(FieldTrace {
+			field: field.to_string(),
+			xpath: source.to_string(),
+			raw: value.clone(),
+			parsed: value.clone(),
+			..FieldTrace::default()
+		});
+
+		value
+	}
+
+	/// Runs the steps on already found values.
+	fn parse(field: &str, opt: &ParseOpts, selector: &str, raw: Result<Vec<String>>) -> (FieldTrace, Result<Option<String>>) {
+		let mut trace = FieldTrace {
+			field: field.to_string(),
+			xpath: selector.to_string(),
+			..FieldTrace::default()
+		};
+
+		let result = raw.and_then(|raw| {
+			trace.raw = match raw.len() {
+				0 | 1 => raw.first().cloned(),
+				_ => serde_json::to_string(&raw).ok()
+			};
+
+			opt.apply(raw)
+		});
 
+		match &result {
+			Ok(v) => trace.parsed = v.clone(),
+			Err(e) => trace.error = Some(e.message())
+		}
 
+		(trace, result)
+	}
 
-pub fn get_optional_string(opt: Option<&ParseOpts>, doc: &Document, node: &Node) -> Result<Option<String>> {
-	let opt = match opt {
-		Some(v) => v,
-		None => return Ok(None)
-	};
+	/// Records an error on a field without failing the node.
+	pub fn flag(&mut self, field: &str, error: &Error) {
+		match self.fields.iter_mut().rev().find(|v| v.field == field) {
+			Some(trace) => trace.error = Some(error.message()),
+			None => self.fields.push(FieldTrace {
+				field: field.to_string(),
+				error: Some(error.message()),
+				..FieldTrace::default()
+			})
+		}
+	}
 
-	let value = match opt.evaluate(doc, node)?.next().transpose()? {
-		Some(v) => v,
-		None => return Ok(None)
-	};
+	/// Records why the node failed.
+	pub fn fail(&mut self, error: &Error) {
+		self.failed_field = match error {
+			Error::MissingField(field) => Some(field.clone()),
+			_ => self.fields.iter().rev().find(|v| v.error.is_some()).map(|v| v.field.clone())
+		};
 
-	Ok(Some(opt.parse(&value.convert_to_string()?)?))
-}
\ No newline at end of file
+		self.error = Some(error.message());
+	}
+}
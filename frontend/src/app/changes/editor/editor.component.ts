@@ -203,6 +203,11 @@ export class ChangesEditorComponent {
 		if (this.editing.match_opts[name]) {
 			let parse_type = (this.editing.match_opts[name] as any).parse_type;
 
+			// Parse steps are a list. Only the first one is edited here.
+			if (Array.isArray(parse_type)) {
+				parse_type = parse_type[0];
+			}
+
 			if (parse_type) {
 				if (typeof parse_type == 'string') {
 					return { name: parse_type, value: null };
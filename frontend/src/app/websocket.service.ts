@@ -198,10 +198,11 @@ export class WebsocketService {
 		return this.send('feed_list', {});
 	}
 
-	public send_create_listener(url: string, custom_item_id: Nullable<number>): Promise<CreateListenerResponse> {
+	public send_create_listener(url: string, custom_item_id: Nullable<number>, url_filter: Nullable<string> = null): Promise<CreateListenerResponse> {
 		let opts = {
 			url: url,
-			custom_item_id: custom_item_id
+			custom_item_id: custom_item_id,
+			url_filter: url_filter
 		};
 
 		return this.send('add_listener', opts);
@@ -238,12 +239,19 @@ export class WebsocketService {
 		return this.send('custom_item_list', {});
 	}
 
-	// public send_update_custom_item(id: number, item: ModelCustomItem): Promise<any> {
-	// 	return this.send_response('update_custom_item', {
-	// 		id,
-	// 		item
-	// 	});
-	// }
+	public send_update_custom_item(id: number, item: Partial<ModelCustomItem>): Promise<UpdateCustomItemResponse> {
+		return this.send('update_custom_item', {
+			id,
+			item
+		});
+	}
+
+	public send_remove_custom_item(id: number, force = false): Promise<RemoveCustomItemResponse> {
+		return this.send('remove_custom_item', {
+			id,
+			force
+		});
+	}
 
 	public send_new_custom_item(item: ModelCustomItem): Promise<CreateCustomItemResponse> {
 		return this.send('new_custom_item', {
@@ -321,10 +329,12 @@ export class WebsocketService {
 		return this.send('watcher_list', {});
 	}
 
-	public send_create_watcher(url: string, custom_item_id: Nullable<number>): Promise<CreateListenerResponse> {
+	public send_create_watcher(url: string, custom_item_id: Nullable<number>, structured_data: Nullable<StructuredValue> = null, page_text: Nullable<PageText> = null): Promise<CreateListenerResponse> {
 		let opts = {
 			url: url,
-			custom_item_id: custom_item_id
+			custom_item_id: custom_item_id,
+			structured_data: structured_data,
+			page_text: page_text
 		};
 
 		return this.send('add_watcher', opts);
@@ -348,15 +358,24 @@ export class WebsocketService {
 		return this.send('remove_watcher', opts);
 	}
 
-	public send_test_watcher(url: string, parser: Nullable<any>): Promise<CreateListenerResponse> {
+	public send_test_watcher(url: string, parser: Nullable<any>, structured_data: Nullable<StructuredValue> = null, page_text: Nullable<PageText> = null): Promise<TestWatcherResponse> {
 		let opts = {
 			url,
-			parser
+			parser,
+			structured_data,
+			page_text
 		};
 
 		return this.send('test_watcher', opts);
 	}
 
+	public send_test_custom_item(url: string, parser: ModelCustomItem['search_opts']): Promise<TestCustomItemResponse> {
+		return this.send('test_custom_item', {
+			url,
+			parser
+		});
+	}
+
 	public send_new_watch_parser(item: ModelWatchParser): Promise<CreateWatchParserResponse> {
 		return this.send('new_watch_parser', {
 			item
@@ -4,6 +4,28 @@ type Obj<I> = { [name: string]: I };
 type Nullable<I> = I | null;
 type Optional<I> = I | undefined;
 
+// Parser `items_language` and field `language`. Defaults to xpath.
+type SelectorLanguage = 'xpath' | 'css' | 'json_path';
+
+// Watcher `structured_data`. What's watched on schema.org Product offers.
+type StructuredValue = 'price' | 'availability';
+
+// Parser `pagination`. Either `next` or `url_template` with a {page} placeholder.
+interface Pagination {
+	next?: string;
+	url_template?: string;
+	max_pages?: number;
+	delay_ms?: number;
+}
+
+// Watcher `page_text`. Watches the page's visible text without a parser.
+interface PageText {
+	selector?: string;
+	language?: 'xpath' | 'css';
+	// Regexes removed from the text before comparing.
+	ignore?: string[];
+}
+
 
 declare namespace rust {
 	type Values = string | number | boolean | null;
@@ -27,11 +49,22 @@ declare namespace rust {
 }
 
 
+interface SocketError {
+	code: string;
+	category: 'input' | 'not_found' | 'conflict' | 'network' | 'parse' | 'database' | 'internal';
+	message: string;
+	details: {
+		field?: string;
+		url?: string;
+		status?: number;
+	};
+}
+
 interface SocketResponse {
 	[name: string]: any;
 
 	message_id?: number;
-	error?: string;
+	error?: SocketError;
 	result?: {
 		method: string;
 		params: { [name: string]: any; };
@@ -80,6 +113,11 @@ interface ModelListener {
 	last_called: number;
 	remove_after: number;
 	sec_interval: number;
+
+	// 0 RSS, 1 Atom, 2 Custom, 3 Sitemap
+	feed_type: number;
+	// Sitemaps only. Same syntax as `match_url`.
+	url_filter?: Nullable<string>;
 }
 
 interface ModelEditListener {
@@ -94,6 +132,8 @@ interface ModelEditListener {
 
 	remove_after?: number;
 	sec_interval?: number;
+
+	url_filter?: string;
 }
 
 interface ModelItem {
@@ -113,6 +153,9 @@ interface ModelItem {
 	is_removed: boolean;
 	tags: string;
 	feed_id: number;
+
+	// `date` is when it was fetched since the source's date couldn't be parsed.
+	date_unparsed: boolean;
 }
 
 interface ModelFeedCategory {
@@ -128,11 +171,15 @@ interface ModelCustomItem {
 	title: string;
 	description: string;
 	match_url: string;
+	priority?: number;
 
+	// `strip_tracking` is the only boolean. `pagination` is a Pagination.
 	search_opts: {
-		[name: string]: Nullable<{
+		[name: string]: Nullable<boolean | Pagination | {
 			xpath: string
 			parse_type: rust.EnumValue
+			language?: SelectorLanguage
+			fallbacks?: string[]
 		} | string>
 	}
 }
@@ -141,11 +188,14 @@ interface ModelEditCustomItem {
 	title?: string;
 	description?: string;
 	match_url?: string;
+	priority?: number;
 
 	search_opts?: {
-		[name: string]: Nullable<{
+		[name: string]: Nullable<boolean | Pagination | {
 			xpath: string
 			parse_type: rust.EnumValue
+			language?: SelectorLanguage
+			fallbacks?: string[]
 		}>
 	}
 }
@@ -188,6 +238,7 @@ interface FilterListResponse {
 
 interface FeedListResponse {
 	items: ModelListener[];
+	degraded: ModelRequestHistoryItem[];
 }
 
 interface CreateListenerResponse {
@@ -227,11 +278,74 @@ interface CreateCustomItemResponse {
 
 interface UpdateCustomItemResponse {
 	affected: number;
-	category: ModelCustomItem;
+	item: Partial<ModelCustomItem>;
+}
+
+interface TestCustomItemResponse {
+	items: {
+		title: string;
+		link: string;
+		guid: string;
+		date: string;
+		// Null if `date` couldn't be parsed.
+		timestamp: Nullable<number>;
+
+		author: Nullable<string>;
+		content: Nullable<string>;
+	}[];
+
+	nodes: NodeTrace[];
+	// Pages fetched.
+	pages: number;
+}
+
+interface TestWatcherResponse {
+	success: boolean;
+	items: {
+		value: string;
+		unique_id: Nullable<string>;
+		title: Nullable<string>;
+		link: Nullable<string>;
+	}[];
+
+	nodes: NodeTrace[];
+
+	matched: Nullable<WatchParserMatch>;
+	candidates: WatchParserMatch[];
+	// Items came from schema.org Product data.
+	structured: boolean;
+	// Pages fetched.
+	pages: number;
+}
+
+interface WatchParserMatch {
+	pattern: string;
+	parser: ModelWatchParser;
+}
+
+interface NodeTrace {
+	index: number;
+	// Page the node was on. 1 is the feed or watcher URL.
+	page: number;
+	fields: {
+		field: string;
+		// The selector the value came from.
+		xpath: string;
+		// Index into `fallbacks` if the primary selector found nothing.
+		fallback?: Nullable<number>;
+
+		raw: Nullable<string>;
+		parsed: Nullable<string>;
+		error: Nullable<string>;
+	}[];
+
+	failed_field: Nullable<string>;
+	error: Nullable<string>;
 }
 
 interface RemoveCustomItemResponse {
 	affected: number;
+	feed_ids: number[];
 }
 
 interface CustomItemListResponse {
@@ -260,6 +374,7 @@ interface RemoveWatchParserResponse {
 
 interface WatcherListResponse {
 	items: [ModelWatcher, ModelWatchHistory][];
+	degraded: ModelRequestHistoryItem[];
 }
 
 interface WatchParserListResponse {
@@ -283,6 +398,10 @@ interface ModelWatcher {
 	remove_after: number;
 	sec_interval: number;
 
+	structured_data?: Nullable<StructuredValue>;
+	// JSON of PageText.
+	page_text?: Nullable<string>;
+
 	alert?: boolean;
 }
 
@@ -297,6 +416,10 @@ interface ModelEditWatcher {
 	remove_after?: number;
 	sec_interval?: number;
 
+	structured_data?: StructuredValue;
+	// JSON of PageText.
+	page_text?: string;
+
 	alert?: boolean;
 }
 
@@ -308,6 +431,9 @@ interface ModelWatchHistory {
 	items: WatchHistoryItem[];
 
 	date_added: number;
+
+	// Line diff of the page text.
+	diff?: Nullable<string>;
 }
 
 interface WatchHistoryItem {
@@ -324,11 +450,15 @@ interface ModelWatchParser {
 	title: string;
 	description: string;
 	match_url: string;
+	priority?: number;
 
+	// `strip_tracking` is the only boolean. `pagination` is a Pagination.
 	match_opts: {
-		[name: string]: Nullable<string | {
+		[name: string]: Nullable<boolean | Pagination | string | {
 			xpath: string
-			parse_type: rust.EnumNone | rust.EnumObject
+			parse_type: rust.EnumNone | rust.EnumObject | (string | rust.EnumObject)[]
+			language?: SelectorLanguage
+			fallbacks?: string[]
 		} | rust.EnumNone>;
 	}
 }
@@ -338,11 +468,14 @@ interface ModelEditWatchParser {
 	title?: string;
 	description?: string;
 	match_url?: string;
+	priority?: number;
 
 	match_opts?: {
-		[name: string]: Nullable<string | {
+		[name: string]: Nullable<boolean | Pagination | string | {
 			xpath: string
-			parse_type: rust.EnumNone | rust.EnumObject
+			parse_type: rust.EnumNone | rust.EnumObject | (string | rust.EnumObject)[]
+			language?: SelectorLanguage
+			fallbacks?: string[]
 		} | rust.EnumNone>;
 	}
 }
@@ -382,4 +515,13 @@ interface ModelRequestHistoryItem {
 	duration: Optional<number>;
 
 	error: Optional<string>;
+
+	node_count: Optional<number>;
+	found_count: Optional<number>;
+	field_failures: Optional<string>;
+	degraded: Optional<string>;
+	// JSON object. Field name to nodes which used a fallback selector.
+	fallback_fields: Optional<string>;
+	// Pages fetched by the parser.
+	page_count: Optional<number>;
 }
\ No newline at end of file